@@ -0,0 +1,371 @@
+use serde_json::Value;
+
+use crate::blacklist::Blacklist;
+use crate::nhentai::{
+    assemble_gallery, build_cover_url, build_image_url, build_relation, build_search_query,
+    encode_query_component, is_valid_gallery_id, normalize_text, slugify_identifier, GalleryTitles,
+    NhentaiGallery, NhentaiRelation, RelationKey, SearchSort, TagBuckets,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ApiSearchPage {
+    pub galleries: Vec<NhentaiGallery>,
+    pub num_pages: Option<u32>,
+}
+
+pub fn build_api_gallery_url(base_url: &str, gallery_id: &str) -> String {
+    format!("{base_url}/api/gallery/{gallery_id}")
+}
+
+pub fn build_api_search_url(
+    base_url: &str,
+    search: &str,
+    page: Option<u32>,
+    sort: SearchSort,
+    languages: &[String],
+    custom_search_params: Option<&str>,
+    blacklist: &Blacklist,
+) -> Option<String> {
+    let query = build_search_query(search, languages, custom_search_params, blacklist)?;
+    let mut url = format!(
+        "{base_url}/api/galleries/search?query={}",
+        encode_query_component(&query)
+    );
+    if let Some(sort) = sort.as_param() {
+        url.push_str(&format!("&sort={sort}"));
+    }
+    if let Some(p) = page {
+        if p > 1 {
+            url.push_str(&format!("&page={p}"));
+        }
+    }
+    Some(url)
+}
+
+/// Galleries carrying one tag (artist, group, parody...) by its numeric id.
+pub fn build_api_tagged_url(
+    base_url: &str,
+    tag_id: u64,
+    page: Option<u32>,
+    sort: SearchSort,
+) -> String {
+    let mut url = format!("{base_url}/api/galleries/tagged?tag_id={tag_id}");
+    if let Some(sort) = sort.as_param() {
+        url.push_str(&format!("&sort={sort}"));
+    }
+    if let Some(p) = page.filter(|p| *p > 1) {
+        url.push_str(&format!("&page={p}"));
+    }
+    url
+}
+
+/// Numeric id of the tag `key` names, read from the tags of an API search response. The
+/// tagged endpoint only takes ids while relation ids carry slugs.
+pub fn find_api_tag_id(body: &str, key: &RelationKey) -> Option<u64> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let listing_path = format!("/{}/{}/", key.namespace, key.slug);
+    value
+        .get("result")?
+        .as_array()?
+        .iter()
+        .filter_map(|gallery| gallery.get("tags")?.as_array())
+        .flatten()
+        .find(|tag| {
+            let field = |name: &str| tag.get(name).and_then(Value::as_str).unwrap_or_default();
+            field("type") == key.namespace
+                && (field("url") == listing_path
+                    || slugify_identifier(field("name")).as_deref() == Some(key.slug.as_str()))
+        })
+        .and_then(|tag| tag.get("id")?.as_u64())
+}
+
+pub fn parse_api_gallery_json(body: &str, base_url: &str) -> Option<NhentaiGallery> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    gallery_from_value(&value, base_url)
+}
+
+pub fn parse_api_search_json(body: &str, base_url: &str) -> Option<ApiSearchPage> {
+    let value: Value = serde_json::from_str(body).ok()?;
+    let results = value.get("result")?.as_array()?;
+
+    Some(ApiSearchPage {
+        galleries: results
+            .iter()
+            .filter_map(|result| gallery_from_value(result, base_url))
+            .collect(),
+        num_pages: value
+            .get("num_pages")
+            .and_then(Value::as_u64)
+            .and_then(|n| u32::try_from(n).ok()),
+    })
+}
+
+/// Next page to request after `current_page`, based on the `num_pages` the API reports.
+pub fn api_next_page(page: &ApiSearchPage, current_page: u32) -> Option<u32> {
+    if page.galleries.is_empty() {
+        return None;
+    }
+    page.num_pages
+        .filter(|total| current_page < *total)
+        .map(|_| current_page + 1)
+}
+
+fn gallery_from_value(value: &Value, base_url: &str) -> Option<NhentaiGallery> {
+    let gallery_id = json_id(value.get("id")?)?;
+    let media_id = json_id(value.get("media_id")?)?;
+
+    let title_of = |key: &str| {
+        value
+            .get("title")
+            .and_then(|titles| titles.get(key))
+            .and_then(Value::as_str)
+            .map(str::to_string)
+    };
+    let titles = GalleryTitles {
+        english: title_of("english"),
+        pretty: title_of("pretty"),
+        japanese: title_of("japanese"),
+    };
+
+    let images_value = value.get("images");
+    let images = images_value
+        .and_then(|images| images.get("pages"))
+        .and_then(Value::as_array)
+        .map(|pages| {
+            pages
+                .iter()
+                .enumerate()
+                .map(|(idx, page)| {
+                    let page_type = page.get("t").and_then(Value::as_str).unwrap_or("j");
+                    build_image_url(&media_id, idx + 1, page_type)
+                })
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+
+    let cover_url = images_value
+        .and_then(|images| images.get("cover"))
+        .and_then(|cover| cover.get("t"))
+        .and_then(Value::as_str)
+        .map(|page_type| build_cover_url(&media_id, page_type))
+        .unwrap_or_default();
+
+    let mut tag_buckets = parse_api_tags(value.get("tags"), base_url);
+    tag_buckets.pages = value
+        .get("num_pages")
+        .and_then(Value::as_u64)
+        .and_then(|n| u32::try_from(n).ok())
+        .filter(|n| *n > 0);
+    tag_buckets.upload_date = value.get("upload_date").and_then(Value::as_i64);
+    tag_buckets.favorites = value
+        .get("num_favorites")
+        .and_then(Value::as_u64)
+        .and_then(|n| u32::try_from(n).ok());
+
+    Some(assemble_gallery(
+        base_url,
+        &gallery_id,
+        &titles,
+        cover_url,
+        images,
+        tag_buckets,
+    ))
+}
+
+fn parse_api_tags(tags: Option<&Value>, base_url: &str) -> TagBuckets {
+    let mut out = TagBuckets::default();
+
+    for tag in tags.and_then(Value::as_array).into_iter().flatten() {
+        let Some(label) = tag.get("type").and_then(Value::as_str) else {
+            continue;
+        };
+        let name = normalize_text(tag.get("name").and_then(Value::as_str).unwrap_or_default());
+        if name.is_empty() {
+            continue;
+        }
+
+        let href = tag.get("url").and_then(Value::as_str).unwrap_or_default();
+        let relation =
+            build_relation(label, href, &name, base_url).map(|relation| NhentaiRelation {
+                count: tag.get("count").and_then(Value::as_u64),
+                ..relation
+            });
+        let (relation_ids, relation_details) = match relation {
+            Some(relation) => (vec![relation.id.clone()], vec![relation]),
+            None => (vec![], vec![]),
+        };
+
+        out.absorb(label, vec![name], relation_ids, relation_details);
+        if let Some(id) = tag.get("id").and_then(Value::as_u64) {
+            if !out.site_tag_ids.contains(&id) {
+                out.site_tag_ids.push(id);
+            }
+        }
+    }
+
+    out
+}
+
+fn json_id(value: &Value) -> Option<String> {
+    let id = match value {
+        Value::Number(n) => n.as_u64()?.to_string(),
+        Value::String(s) => s.trim().to_string(),
+        _ => return None,
+    };
+
+    if is_valid_gallery_id(&id) {
+        Some(id)
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nhentai::{NhentaiRelation, DEFAULT_BASE_URL};
+
+    const GALLERY_JSON: &str = r#"{
+        "id": 12345,
+        "media_id": "555",
+        "title": {
+            "english": "[Circle (Artist)] Sample Gallery [English]",
+            "japanese": "サンプル",
+            "pretty": "Sample Gallery"
+        },
+        "images": {
+            "pages": [{"t": "j", "w": 1280, "h": 1800}, {"t": "p", "w": 1280, "h": 1800}],
+            "cover": {"t": "j", "w": 350, "h": 500},
+            "thumbnail": {"t": "j", "w": 250, "h": 350}
+        },
+        "tags": [
+            {"id": 1, "type": "artist", "name": "artist one", "url": "/artist/artist-one/", "count": 10},
+            {"id": 2, "type": "tag", "name": "full color", "url": "/tag/full-color/", "count": 1000},
+            {"id": 12227, "type": "language", "name": "english", "url": "/language/english/", "count": 5000},
+            {"id": 33172, "type": "category", "name": "doujinshi", "url": "/category/doujinshi/", "count": 9000}
+        ],
+        "num_pages": 2,
+        "upload_date": 1619872496,
+        "num_favorites": 42
+    }"#;
+
+    #[test]
+    fn build_api_search_url_reuses_search_query() {
+        let english = vec!["english".to_string()];
+        let url = build_api_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            Some(2),
+            SearchSort::Popular,
+            &english,
+            Some("-yaoi"),
+            &Blacklist::default(),
+        )
+        .expect("url");
+        assert_eq!(
+            url,
+            "https://nhentai.net/api/galleries/search?query=language%3Aenglish+soft+-yaoi&sort=popular&page=2"
+        );
+        assert!(build_api_search_url(
+            DEFAULT_BASE_URL,
+            "  ",
+            None,
+            SearchSort::Recent,
+            &english,
+            None,
+            &Blacklist::default()
+        )
+        .is_none());
+    }
+
+    #[test]
+    fn build_api_tagged_url_pages_and_sorts() {
+        assert_eq!(
+            build_api_tagged_url(DEFAULT_BASE_URL, 1, None, SearchSort::Recent),
+            "https://nhentai.net/api/galleries/tagged?tag_id=1"
+        );
+        assert_eq!(
+            build_api_tagged_url(DEFAULT_BASE_URL, 1, Some(3), SearchSort::Popular),
+            "https://nhentai.net/api/galleries/tagged?tag_id=1&sort=popular&page=3"
+        );
+    }
+
+    #[test]
+    fn find_api_tag_id_matches_namespace_and_slug() {
+        let body = format!(r#"{{"result": [{GALLERY_JSON}], "num_pages": 1}}"#);
+        let key = |id: &str| crate::nhentai::parse_relation_key(id).expect("relation key");
+        assert_eq!(
+            find_api_tag_id(&body, &key("nhentai-artist:artist-one")),
+            Some(1)
+        );
+        assert_eq!(
+            find_api_tag_id(&body, &key("nhentai-tags:full-color")),
+            Some(2)
+        );
+        assert_eq!(
+            find_api_tag_id(&body, &key("nhentai-group:artist-one")),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_api_gallery_json_fills_gallery() {
+        let gallery =
+            parse_api_gallery_json(GALLERY_JSON, DEFAULT_BASE_URL).expect("gallery should parse");
+        assert_eq!(gallery.id, Some("12345".to_string()));
+        assert_eq!(gallery.title, "Sample Gallery");
+        assert_eq!(gallery.alternate_titles, vec!["サンプル".to_string()]);
+        assert_eq!(gallery.japanese_title, Some("サンプル".to_string()));
+        assert_eq!(gallery.upload_date, Some(1_619_872_496));
+        assert_eq!(gallery.favorites, Some(42));
+        assert_eq!(gallery.gallery_url, "https://nhentai.net/g/12345/");
+        assert_eq!(
+            gallery.cover_url,
+            "https://t.nhentai.net/galleries/555/cover.jpg"
+        );
+        assert_eq!(
+            gallery.images,
+            vec![
+                "https://i.nhentai.net/galleries/555/1.jpg".to_string(),
+                "https://i.nhentai.net/galleries/555/2.png".to_string()
+            ]
+        );
+        assert_eq!(gallery.pages, Some(2));
+        assert_eq!(gallery.artists, vec!["artist one".to_string()]);
+        assert_eq!(gallery.languages, vec!["english".to_string()]);
+        assert_eq!(
+            gallery.people_details,
+            vec![NhentaiRelation {
+                id: "nhentai-artist:artist-one".to_string(),
+                name: "artist one".to_string(),
+                count: Some(10),
+            }]
+        );
+        assert_eq!(
+            gallery.tag_ids,
+            vec![
+                "nhentai-tags:full-color".to_string(),
+                "nhentai-language:english".to_string(),
+                "nhentai-category:doujinshi".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_api_gallery_json_rejects_error_payload() {
+        assert!(
+            parse_api_gallery_json(r#"{"error": "does not exist"}"#, DEFAULT_BASE_URL).is_none()
+        );
+        assert!(parse_api_gallery_json("<html></html>", DEFAULT_BASE_URL).is_none());
+    }
+
+    #[test]
+    fn parse_api_search_json_reads_results_and_page_count() {
+        let body = format!(r#"{{"result": [{GALLERY_JSON}], "num_pages": 3, "per_page": 25}}"#);
+        let page = parse_api_search_json(&body, DEFAULT_BASE_URL).expect("search should parse");
+        assert_eq!(page.galleries.len(), 1);
+        assert_eq!(page.num_pages, Some(3));
+        assert_eq!(api_next_page(&page, 1), Some(2));
+        assert_eq!(api_next_page(&page, 3), None);
+    }
+}
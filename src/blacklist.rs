@@ -0,0 +1,176 @@
+use crate::nhentai::{NhentaiGallery, NhentaiRelation};
+use crate::query::SearchQuery;
+
+/// Return code when a directly requested gallery is refused for a blacklisted tag,
+/// distinct from an upstream 403 and from `challenge::BLOCKED_ERROR_CODE`.
+pub const BLACKLISTED_ERROR_CODE: i32 = 1451;
+
+/// One blacklisted relation, e.g. `tag:netorare` or `artist:some-artist`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlacklistEntry {
+    pub namespace: String,
+    pub value: String,
+}
+
+impl BlacklistEntry {
+    pub fn describe(&self) -> String {
+        format!("{}:{}", self.namespace, self.value)
+    }
+}
+
+/// Tags that are never imported: excluded from search queries and filtered out of results.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Blacklist {
+    pub entries: Vec<BlacklistEntry>,
+}
+
+impl Blacklist {
+    /// Parses a comma separated list; bare values are tags (`netorare, artist:someone`).
+    pub fn parse(value: &str) -> Blacklist {
+        let entries = value
+            .split(',')
+            .filter_map(|part| {
+                let part = part.trim().trim_start_matches('-');
+                let (namespace, value) = match part.split_once(':') {
+                    Some((namespace, value)) => (canonical_namespace(namespace)?, value),
+                    None => ("tag", part),
+                };
+                let value = normalize(value);
+                if value.is_empty() {
+                    return None;
+                }
+                Some(BlacklistEntry {
+                    namespace: namespace.to_string(),
+                    value,
+                })
+            })
+            .collect();
+        Blacklist { entries }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Adds a negated term per entry, so the site drops those galleries itself.
+    pub fn exclude_from(&self, query: SearchQuery) -> SearchQuery {
+        self.entries.iter().fold(query, |query, entry| {
+            query.exclude(&entry.namespace, &entry.value)
+        })
+    }
+
+    /// The first entry `gallery` carries, checked against both names and relation ids.
+    pub fn matches(&self, gallery: &NhentaiGallery) -> Option<&BlacklistEntry> {
+        self.entries.iter().find(|entry| {
+            let (names, relation_key) = match entry.namespace.as_str() {
+                "tag" => (&gallery.tags, "tags"),
+                "artist" => (&gallery.artists, "artist"),
+                "group" => (&gallery.groups, "group"),
+                "parody" => (&gallery.parodies, "parody"),
+                "character" => (&gallery.characters, "character"),
+                "language" => (&gallery.languages, "language"),
+                "category" => (&gallery.categories, "category"),
+                _ => return false,
+            };
+            names.iter().any(|name| normalize(name) == entry.value)
+                || [
+                    &gallery.tag_details,
+                    &gallery.people_details,
+                    &gallery.parody_details,
+                ]
+                .into_iter()
+                .flatten()
+                .any(|relation| relation_matches(relation, relation_key, &entry.value))
+        })
+    }
+}
+
+fn relation_matches(relation: &NhentaiRelation, relation_key: &str, value: &str) -> bool {
+    relation
+        .id
+        .strip_prefix("nhentai-")
+        .and_then(|id| id.split_once(':'))
+        .is_some_and(|(key, slug)| key == relation_key && normalize(slug) == value)
+}
+
+fn canonical_namespace(namespace: &str) -> Option<&'static str> {
+    match namespace.trim().to_ascii_lowercase().as_str() {
+        "tag" | "tags" => Some("tag"),
+        "artist" | "artists" => Some("artist"),
+        "group" | "groups" => Some("group"),
+        "parody" | "parodies" => Some("parody"),
+        "character" | "characters" => Some("character"),
+        "language" | "languages" => Some("language"),
+        "category" | "categories" => Some("category"),
+        _ => None,
+    }
+}
+
+/// Case-insensitive, with slug hyphens read as spaces (`big-breasts` == `Big Breasts`).
+fn normalize(value: &str) -> String {
+    value
+        .trim()
+        .trim_matches('"')
+        .to_lowercase()
+        .replace('-', " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_namespaces_and_defaults_to_tag() {
+        let blacklist =
+            Blacklist::parse(" Netorare, artist:Some-Artist, bogus:x , ,-tag:\"big breasts\"");
+        let described: Vec<String> = blacklist.entries.iter().map(|e| e.describe()).collect();
+        assert_eq!(
+            described,
+            vec!["tag:netorare", "artist:some artist", "tag:big breasts"]
+        );
+    }
+
+    #[test]
+    fn exclude_from_renders_negated_terms() {
+        let blacklist = Blacklist::parse("netorare, big breasts");
+        assert_eq!(
+            blacklist.exclude_from(SearchQuery::parse("soft")).render(),
+            r#"soft -tag:netorare -tag:"big breasts""#
+        );
+    }
+
+    #[test]
+    fn matches_names_and_relation_ids() {
+        let blacklist = Blacklist::parse("big breasts, artist:some artist");
+        let by_name = NhentaiGallery {
+            tags: vec!["Big Breasts".to_string()],
+            ..Default::default()
+        };
+        let by_relation = NhentaiGallery {
+            people_details: vec![NhentaiRelation {
+                id: "nhentai-artist:some-artist".to_string(),
+                name: "Some Artist".to_string(),
+                count: None,
+            }],
+            ..Default::default()
+        };
+        let clean = NhentaiGallery {
+            tags: vec!["full color".to_string()],
+            artists: vec!["other".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            blacklist.matches(&by_name).map(|e| e.describe()),
+            Some("tag:big breasts".to_string())
+        );
+        assert_eq!(
+            blacklist.matches(&by_relation).map(|e| e.describe()),
+            Some("artist:some artist".to_string())
+        );
+        assert_eq!(blacklist.matches(&clean), None);
+    }
+}
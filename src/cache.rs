@@ -0,0 +1,284 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use extism_pdk::{log, var, LogLevel};
+use serde_json::{json, Value};
+
+/// Default number of plugin calls a cached response stays fresh. wasm32-unknown-unknown has no
+/// clock, so age is counted in calls: the host's `lookup_metadata` followed by
+/// `lookup_metadata_images` for the same book is two calls apart.
+pub const DEFAULT_CACHE_TTL_CALLS: u64 = 10;
+pub const DEFAULT_CACHE_MAX_ENTRIES: usize = 16;
+
+/// Extism caps the total size of plugin vars (1 MiB by default) and traps when a write would
+/// exceed it, so cached bodies stay well below that budget.
+const MAX_CACHE_BYTES: usize = 512 * 1024;
+
+const INDEX_VAR: &str = "nh_cache_index";
+const ENTRY_VAR_PREFIX: &str = "nh_cache:";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CacheEntry {
+    pub url: String,
+    /// Call counter value when the response was stored.
+    pub stored_at: u64,
+    pub size: usize,
+}
+
+/// Bookkeeping for the cached responses; the bodies live in their own vars.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct CacheIndex {
+    /// Incremented once per plugin call.
+    pub clock: u64,
+    /// Oldest first.
+    pub entries: Vec<CacheEntry>,
+}
+
+impl CacheIndex {
+    pub fn from_json(json: &str) -> Option<CacheIndex> {
+        let value: Value = serde_json::from_str(json).ok()?;
+        let entries = value
+            .get("entries")?
+            .as_array()?
+            .iter()
+            .map(|entry| {
+                Some(CacheEntry {
+                    url: entry.get("url")?.as_str()?.to_string(),
+                    stored_at: entry.get("storedAt")?.as_u64()?,
+                    size: usize::try_from(entry.get("size")?.as_u64()?).ok()?,
+                })
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(CacheIndex {
+            clock: value.get("clock")?.as_u64()?,
+            entries,
+        })
+    }
+
+    pub fn to_json(&self) -> String {
+        let entries: Vec<Value> = self
+            .entries
+            .iter()
+            .map(|e| json!({ "url": e.url, "storedAt": e.stored_at, "size": e.size }))
+            .collect();
+        json!({ "clock": self.clock, "entries": entries }).to_string()
+    }
+
+    pub fn is_fresh(&self, url: &str, ttl: u64) -> bool {
+        self.entries
+            .iter()
+            .any(|e| e.url == url && self.clock.saturating_sub(e.stored_at) < ttl)
+    }
+
+    /// Drops entries older than `ttl` calls and returns their URLs.
+    pub fn expire(&mut self, ttl: u64) -> Vec<String> {
+        let clock = self.clock;
+        let (expired, kept): (Vec<_>, Vec<_>) = self
+            .entries
+            .drain(..)
+            .partition(|e| clock.saturating_sub(e.stored_at) >= ttl);
+        self.entries = kept;
+        expired.into_iter().map(|e| e.url).collect()
+    }
+
+    /// Records `url` as the newest entry, evicting the oldest ones until both the entry and
+    /// byte bounds hold. Returns the evicted URLs (never `url` itself).
+    pub fn insert(
+        &mut self,
+        url: &str,
+        size: usize,
+        max_entries: usize,
+        max_bytes: usize,
+    ) -> Vec<String> {
+        self.entries.retain(|e| e.url != url);
+        let mut evicted = Vec::new();
+        while !self.entries.is_empty()
+            && (self.entries.len() + 1 > max_entries
+                || self.total_size().saturating_add(size) > max_bytes)
+        {
+            evicted.push(self.entries.remove(0).url);
+        }
+        self.entries.push(CacheEntry {
+            url: url.to_string(),
+            stored_at: self.clock,
+            size,
+        });
+        evicted
+    }
+
+    fn total_size(&self) -> usize {
+        self.entries.iter().map(|e| e.size).sum()
+    }
+}
+
+/// Response cache shared across plugin calls through Extism vars. Failures are logged and
+/// treated as a cache miss so they never break a lookup.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResponseCache {
+    /// Freshness in plugin calls; 0 disables the cache.
+    pub ttl_calls: u64,
+    pub max_entries: usize,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        ResponseCache {
+            ttl_calls: DEFAULT_CACHE_TTL_CALLS,
+            max_entries: DEFAULT_CACHE_MAX_ENTRIES,
+        }
+    }
+}
+
+impl ResponseCache {
+    fn enabled(&self) -> bool {
+        self.ttl_calls > 0 && self.max_entries > 0
+    }
+
+    /// Advances the call counter and drops expired responses; run once per plugin call.
+    pub fn begin_call(&self) {
+        if !self.enabled() {
+            return;
+        }
+        let mut index = load_index();
+        index.clock += 1;
+        for url in index.expire(self.ttl_calls) {
+            remove_entry(&url);
+        }
+        save_index(&index);
+    }
+
+    pub fn get(&self, url: &str) -> Option<String> {
+        if !self.enabled() || !load_index().is_fresh(url, self.ttl_calls) {
+            return None;
+        }
+        let body = var::get::<String>(entry_var(url)).ok().flatten()?;
+        log!(LogLevel::Debug, "nhentai cache hit: {}", url);
+        Some(body)
+    }
+
+    pub fn put(&self, url: &str, body: &str) {
+        if !self.enabled() || body.len() > MAX_CACHE_BYTES {
+            return;
+        }
+        let mut index = load_index();
+        for evicted in index.insert(url, body.len(), self.max_entries, MAX_CACHE_BYTES) {
+            remove_entry(&evicted);
+        }
+        if let Err(e) = var::set(entry_var(url), body) {
+            log!(
+                LogLevel::Warn,
+                "nhentai cache write failed for {}: {}",
+                url,
+                e
+            );
+            return;
+        }
+        save_index(&index);
+    }
+}
+
+/// Cache key of a response: its URL, plus a hash of the session cookie it was fetched
+/// with, so a page seen through one session (or `cf_clearance`) is never served to another.
+pub fn cache_key(url: &str, cookie: Option<&str>) -> String {
+    let Some(cookie) = cookie else {
+        return url.to_string();
+    };
+    let mut hasher = DefaultHasher::new();
+    cookie.hash(&mut hasher);
+    format!("{url}#session-{:016x}", hasher.finish())
+}
+
+fn entry_var(url: &str) -> String {
+    format!("{ENTRY_VAR_PREFIX}{url}")
+}
+
+fn load_index() -> CacheIndex {
+    var::get::<String>(INDEX_VAR)
+        .ok()
+        .flatten()
+        .and_then(|json| CacheIndex::from_json(&json))
+        .unwrap_or_default()
+}
+
+fn save_index(index: &CacheIndex) {
+    if let Err(e) = var::set(INDEX_VAR, index.to_json()) {
+        log!(LogLevel::Warn, "nhentai cache index write failed: {}", e);
+    }
+}
+
+fn remove_entry(url: &str) {
+    if let Err(e) = var::remove(entry_var(url)) {
+        log!(
+            LogLevel::Warn,
+            "nhentai cache eviction failed for {}: {}",
+            url,
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cache_key_separates_sessions() {
+        let url = "https://nhentai.net/g/1/";
+        assert_eq!(cache_key(url, None), url);
+        let one = cache_key(url, Some("sessionid=one"));
+        assert!(one.starts_with(url));
+        assert_eq!(one, cache_key(url, Some("sessionid=one")));
+        assert_ne!(one, cache_key(url, Some("sessionid=two")));
+    }
+
+    #[test]
+    fn entries_expire_after_ttl_calls() {
+        let mut index = CacheIndex::default();
+        index.insert("https://nhentai.net/g/1/", 10, 8, 1024);
+        index.clock = 1;
+        assert!(index.is_fresh("https://nhentai.net/g/1/", 2));
+        assert!(index.expire(2).is_empty());
+
+        index.clock = 2;
+        assert!(!index.is_fresh("https://nhentai.net/g/1/", 2));
+        assert_eq!(
+            index.expire(2),
+            vec!["https://nhentai.net/g/1/".to_string()]
+        );
+        assert!(index.entries.is_empty());
+    }
+
+    #[test]
+    fn insert_evicts_oldest_over_entry_bound() {
+        let mut index = CacheIndex::default();
+        index.insert("a", 1, 2, 1024);
+        index.insert("b", 1, 2, 1024);
+        assert_eq!(index.insert("c", 1, 2, 1024), vec!["a".to_string()]);
+        let urls: Vec<_> = index.entries.iter().map(|e| e.url.as_str()).collect();
+        assert_eq!(urls, vec!["b", "c"]);
+    }
+
+    #[test]
+    fn insert_evicts_until_bytes_fit_and_refreshes_existing_url() {
+        let mut index = CacheIndex::default();
+        index.insert("a", 400, 8, 1000);
+        index.insert("b", 400, 8, 1000);
+        assert_eq!(index.insert("c", 400, 8, 1000), vec!["a".to_string()]);
+
+        index.clock = 5;
+        assert!(index.insert("b", 400, 8, 1000).is_empty());
+        assert_eq!(index.entries.last().map(|e| e.stored_at), Some(5));
+        assert_eq!(index.entries.len(), 2);
+    }
+
+    #[test]
+    fn index_round_trips_through_json() {
+        let mut index = CacheIndex {
+            clock: 3,
+            ..Default::default()
+        };
+        index.insert("https://nhentai.net/search/?q=x", 42, 8, 1024);
+        assert_eq!(CacheIndex::from_json(&index.to_json()), Some(index));
+        assert_eq!(CacheIndex::from_json("not json"), None);
+    }
+}
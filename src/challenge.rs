@@ -0,0 +1,83 @@
+use std::collections::HashMap;
+
+/// Return code used when nhentai answers with a bot challenge instead of content. It is
+/// outside the HTTP range so the host can tell it apart from an upstream 403, a
+/// blacklisted gallery and "nothing matched" (404).
+pub const BLOCKED_ERROR_CODE: i32 = 1403;
+
+/// Titles of Cloudflare's interstitial pages, which replace the whole document.
+const TITLE_MARKERS: [&str; 2] = [
+    "<title>Just a moment...</title>",
+    "<title>Attention Required! | Cloudflare</title>",
+];
+
+/// Challenge script markers. Regular pages can load the same scripts, so these only
+/// count on error responses.
+const BODY_MARKERS: [&str; 5] = [
+    "cf-chl-",
+    "cf_chl_opt",
+    "/cdn-cgi/challenge-platform/",
+    "cf-browser-verification",
+    "Enable JavaScript and cookies to continue",
+];
+
+/// Detects Cloudflare challenge / block pages from the response status, headers and body.
+pub fn is_challenge_page(status: u16, headers: &HashMap<String, String>, body: &str) -> bool {
+    let mitigated = headers.iter().any(|(name, value)| {
+        name.eq_ignore_ascii_case("cf-mitigated") && value.eq_ignore_ascii_case("challenge")
+    });
+    if mitigated {
+        return true;
+    }
+
+    // The markers sit in the head of a challenge page; only inspect that to keep this cheap.
+    let head = body
+        .char_indices()
+        .nth(16 * 1024)
+        .map_or(body, |(idx, _)| &body[..idx]);
+    if TITLE_MARKERS.iter().any(|marker| head.contains(marker)) {
+        return true;
+    }
+    !(200..300).contains(&status) && BODY_MARKERS.iter().any(|marker| head.contains(marker))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detects_just_a_moment_page() {
+        let body = r#"<!DOCTYPE html><html><head><title>Just a moment...</title></head>
+            <body><script>window._cf_chl_opt={cvId: '3'};</script></body></html>"#;
+        assert!(is_challenge_page(200, &HashMap::new(), body));
+        assert!(is_challenge_page(503, &HashMap::new(), body));
+    }
+
+    #[test]
+    fn challenge_scripts_only_count_on_error_responses() {
+        let body = r#"<html><head><title>Soft Love - nhentai</title>
+            <script src="/cdn-cgi/challenge-platform/scripts/jsd/main.js"></script></head>
+            <body><div id="info"></div></body></html>"#;
+        assert!(!is_challenge_page(200, &HashMap::new(), body));
+        assert!(is_challenge_page(403, &HashMap::new(), body));
+    }
+
+    #[test]
+    fn detects_cf_mitigated_header() {
+        let mut headers = HashMap::new();
+        headers.insert("Cf-Mitigated".to_string(), "challenge".to_string());
+        assert!(is_challenge_page(403, &headers, ""));
+    }
+
+    #[test]
+    fn regular_search_page_is_not_a_challenge() {
+        let body = r#"<html><head><title>Search - nhentai</title></head><body>
+            <div class="gallery"><a class="cover" href="/g/1/"></a></div></body></html>"#;
+        assert!(!is_challenge_page(200, &HashMap::new(), body));
+        assert!(!is_challenge_page(
+            200,
+            &HashMap::new(),
+            r#"{"result": []}"#
+        ));
+    }
+}
@@ -12,11 +12,15 @@ use rs_plugin_common_interfaces::{
 };
 use serde_json::json;
 
+use crate::date::year_of;
 use crate::nhentai::{NhentaiGallery, NhentaiRelation};
 
 pub fn nhentai_gallery_to_result(item: NhentaiGallery) -> RsLookupMetadataResultWrapper {
     let images = nhentai_gallery_to_images(&item);
     let language_code = default_language_code(&item.languages);
+    let info = &item.title_info;
+    // Circle, translator and event read from the title only go in the params: a relation
+    // id has to resolve back to galleries, and a slug made from the title text may not.
     let people_details = build_people_details(&item.people_details);
     let tag_details = build_tag_details(&item.tag_details);
     let series = build_series(&item.parody_details);
@@ -37,6 +41,16 @@ pub fn nhentai_gallery_to_result(item: NhentaiGallery) -> RsLookupMetadataResult
         "characters": item.characters,
         "languages": item.languages,
         "categories": item.categories,
+        "event": info.event,
+        "circle": info.circle,
+        "translator": info.translator,
+        "digital": info.digital,
+        "decensored": info.decensored,
+        "colorized": info.colorized,
+        "ongoing": info.ongoing,
+        "alternateTitles": item.alternate_titles,
+        "japaneseTitle": item.japanese_title,
+        "favorites": item.favorites,
     });
 
     let book = Book {
@@ -46,6 +60,10 @@ pub fn nhentai_gallery_to_result(item: NhentaiGallery) -> RsLookupMetadataResult
         lang: language_code,
         pages: item.pages,
         params: Some(params),
+        original: item.japanese_title,
+        year: item.upload_date.and_then(year_of),
+        // The host's timestamps are in milliseconds; nhentai's are in seconds.
+        airdate: item.upload_date.map(|date| date * 1000),
         ..Default::default()
     };
 
@@ -128,12 +146,18 @@ fn build_people_details(values: &[NhentaiRelation]) -> Vec<Person> {
         .map(|value| Person {
             id: value.id.clone(),
             name: value.name.clone(),
+            params: relation_params(value),
             generated: true,
             ..Default::default()
         })
         .collect()
 }
 
+/// The site's usage count, so tags and people can be ranked by popularity.
+fn relation_params(value: &NhentaiRelation) -> Option<serde_json::Value> {
+    value.count.map(|count| json!({ "count": count }))
+}
+
 fn build_tag_details(values: &[NhentaiRelation]) -> Vec<Tag> {
     values
         .iter()
@@ -145,7 +169,7 @@ fn build_tag_details(values: &[NhentaiRelation]) -> Vec<Tag> {
             kind: None,
             alt: None,
             thumb: None,
-            params: None,
+            params: relation_params(value),
             modified: 0,
             added: 0,
             generated: true,
@@ -161,7 +185,7 @@ fn build_series(values: &[NhentaiRelation]) -> Vec<Serie> {
         .filter(|value| {
             !value.id.trim().is_empty()
                 && !value.name.trim().is_empty()
-                && value.name.to_ascii_lowercase() != "original"
+                && !value.name.eq_ignore_ascii_case("original")
         })
         .map(|value| Serie {
             id: value.id.clone(),
@@ -196,6 +220,7 @@ fn fallback_local_id(title: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::title_info::TitleInfo;
 
     #[test]
     fn maps_gallery_to_book_result() {
@@ -204,6 +229,9 @@ mod tests {
             title: "Soft Sample".to_string(),
             cover_url: "https://t3.nhentai.net/galleries/111/thumb.jpg".to_string(),
             gallery_url: "https://nhentai.net/g/12345/".to_string(),
+            alternate_titles: vec!["ソフトサンプル".to_string()],
+            japanese_title: Some("ソフトサンプル".to_string()),
+            upload_date: Some(1_619_872_496),
             ..Default::default()
         });
 
@@ -211,6 +239,13 @@ mod tests {
             assert_eq!(book.id, "nhentai:12345");
             assert_eq!(book.name, "Soft Sample");
             assert_eq!(book.lang, Some("en".to_string()));
+            assert_eq!(book.original, Some("ソフトサンプル".to_string()));
+            assert_eq!(book.year, Some(2021));
+            assert_eq!(book.airdate, Some(1_619_872_496_000));
+            assert_eq!(
+                book.params.as_ref().and_then(|p| p.get("alternateTitles")),
+                Some(&json!(["ソフトサンプル"]))
+            );
         } else {
             panic!("Expected book metadata");
         }
@@ -256,10 +291,12 @@ mod tests {
             people_details: vec![NhentaiRelation {
                 id: "nhentai-artist:bai-asuka".to_string(),
                 name: "bai asuka".to_string(),
+                count: Some(431),
             }],
             tag_details: vec![NhentaiRelation {
                 id: "nhentai-tags:full-color".to_string(),
                 name: "full color".to_string(),
+                count: Some(12_000),
             }],
             ..Default::default()
         });
@@ -272,10 +309,42 @@ mod tests {
         assert_eq!(people[0].name, "bai asuka");
         assert_eq!(tags[0].id, "nhentai-tags:full-color");
         assert_eq!(tags[0].name, "full color");
+        assert_eq!(people[0].params, Some(json!({ "count": 431 })));
+        assert_eq!(tags[0].params, Some(json!({ "count": 12_000 })));
         assert!(relations.people.is_none());
         assert!(relations.tags.is_none());
     }
 
+    #[test]
+    fn maps_title_info_to_params_and_relations() {
+        let result = nhentai_gallery_to_result(NhentaiGallery {
+            id: Some("12345".to_string()),
+            title: "Soft Sample".to_string(),
+            title_info: TitleInfo {
+                event: Some("C99".to_string()),
+                circle: Some("Circle Name".to_string()),
+                translator: Some("Some Scans".to_string()),
+                digital: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+
+        let RsLookupMetadataResult::Book(book) = &result.metadata else {
+            panic!("Expected book metadata");
+        };
+        let params = book.params.as_ref().expect("expected params");
+        assert_eq!(params["event"], "C99");
+        assert_eq!(params["circle"], "Circle Name");
+        assert_eq!(params["translator"], "Some Scans");
+        assert_eq!(params["digital"], true);
+        assert_eq!(params["decensored"], false);
+
+        let relations = result.relations.expect("expected relations");
+        assert!(relations.people_details.is_none());
+        assert!(relations.tags_details.is_none());
+    }
+
     #[test]
     fn maps_parodies_as_series_details_skipping_original() {
         let result = nhentai_gallery_to_result(NhentaiGallery {
@@ -287,10 +356,12 @@ mod tests {
                 NhentaiRelation {
                     id: "nhentai-parody:naruto".to_string(),
                     name: "naruto".to_string(),
+                    count: None,
                 },
                 NhentaiRelation {
                     id: "nhentai-parody:original".to_string(),
                     name: "original".to_string(),
+                    count: None,
                 },
             ],
             ..Default::default()
@@ -0,0 +1,132 @@
+/// Unix seconds of an ISO 8601 datetime such as nhentai's
+/// `2021-05-01T12:34:56.789012+00:00`. Fractions are dropped; a missing offset is UTC.
+pub fn parse_datetime(value: &str) -> Option<i64> {
+    let value = value.trim();
+    let (date, time) = value.split_once(['T', ' ']).unwrap_or((value, "00:00:00"));
+
+    let mut date_parts = date.splitn(3, '-');
+    let year: i64 = date_parts.next()?.parse().ok()?;
+    let month: u32 = date_parts.next()?.parse().ok()?;
+    let day: u32 = date_parts.next()?.parse().ok()?;
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return None;
+    }
+
+    let (clock, offset) = split_offset(time)?;
+    let clock = clock.split('.').next().unwrap_or(clock);
+    let mut clock_parts = clock.split(':');
+    let hour: i64 = clock_parts.next()?.parse().ok()?;
+    let minute: i64 = clock_parts.next().unwrap_or("0").parse().ok()?;
+    let second: i64 = clock_parts.next().unwrap_or("0").parse().ok()?;
+
+    let days = days_from_civil(year, month, day);
+    Some(days * 86_400 + hour * 3_600 + minute * 60 + second - offset)
+}
+
+/// Unix seconds of an HTTP-date in the IMF-fixdate form, `Wed, 21 Oct 2015 07:28:00 GMT`.
+pub fn parse_http_date(value: &str) -> Option<i64> {
+    let parts: Vec<&str> = value.split_whitespace().collect();
+    let [_, day, month, year, clock, "GMT"] = parts.as_slice() else {
+        return None;
+    };
+    const MONTHS: [&str; 12] = [
+        "Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec",
+    ];
+    let month = MONTHS
+        .iter()
+        .position(|name| name.eq_ignore_ascii_case(month))?
+        + 1;
+    parse_datetime(&format!("{year}-{month:02}-{day}T{clock}Z"))
+}
+
+/// Calendar year of unix seconds, in UTC.
+pub fn year_of(timestamp: i64) -> Option<u16> {
+    let days = timestamp.div_euclid(86_400);
+    // Inverse of `days_from_civil`, keeping only the year.
+    let era_days = days + 719_468;
+    let era = era_days.div_euclid(146_097);
+    let day_of_era = era_days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let year = year_of_era + era * 400 + i64::from(month_index >= 10);
+    u16::try_from(year).ok()
+}
+
+/// Clock part and its UTC offset in seconds (`Z`, `+09:00`, `-0500` or none).
+fn split_offset(time: &str) -> Option<(&str, i64)> {
+    if let Some(clock) = time.strip_suffix(['Z', 'z']) {
+        return Some((clock, 0));
+    }
+    let Some(idx) = time.rfind(['+', '-']) else {
+        return Some((time, 0));
+    };
+    let (clock, offset) = time.split_at(idx);
+    let sign = if offset.starts_with('-') { -1 } else { 1 };
+    let digits: String = offset[1..].chars().filter(char::is_ascii_digit).collect();
+    if digits.len() != 4 {
+        return None;
+    }
+    let hours: i64 = digits[..2].parse().ok()?;
+    let minutes: i64 = digits[2..].parse().ok()?;
+    Some((clock, sign * (hours * 3_600 + minutes * 60)))
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year.rem_euclid(400);
+    let month = i64::from(month);
+    let shifted_month = if month > 2 { month - 3 } else { month + 9 };
+    let day_of_year = (153 * shifted_month + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_datetime_reads_nhentai_format_and_offsets() {
+        assert_eq!(
+            parse_datetime("2021-05-01T12:34:56.789012+00:00"),
+            Some(1_619_872_496)
+        );
+        assert_eq!(
+            parse_datetime("2021-05-01T21:34:56+09:00"),
+            Some(1_619_872_496)
+        );
+        assert_eq!(parse_datetime("2021-05-01T12:34:56Z"), Some(1_619_872_496));
+        assert_eq!(parse_datetime("1970-01-01"), Some(0));
+        assert_eq!(parse_datetime("3 years ago"), None);
+        assert_eq!(parse_datetime("2021-13-01"), None);
+    }
+
+    #[test]
+    fn parse_http_date_reads_imf_fixdate() {
+        assert_eq!(
+            parse_http_date("Sat, 01 May 2021 12:34:56 GMT"),
+            Some(1_619_872_496)
+        );
+        assert_eq!(parse_http_date("Saturday, 01-May-21 12:34:56 GMT"), None);
+        assert_eq!(parse_http_date("120"), None);
+    }
+
+    #[test]
+    fn year_of_handles_year_boundaries() {
+        assert_eq!(year_of(1_619_872_496), Some(2021));
+        assert_eq!(year_of(0), Some(1970));
+        assert_eq!(
+            parse_datetime("2019-12-31T23:59:59Z").and_then(year_of),
+            Some(2019)
+        );
+        assert_eq!(
+            parse_datetime("2020-01-01T00:00:00Z").and_then(year_of),
+            Some(2020)
+        );
+        assert_eq!(year_of(-1), Some(1969));
+    }
+}
@@ -0,0 +1,248 @@
+use crate::nhentai::NhentaiGallery;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ImageHostStrategy {
+    /// Always use the first configured host.
+    Fixed,
+    /// Rotate through the hosts page by page.
+    RoundRobin,
+    /// Pin each gallery to one host picked from its id, spreading galleries across hosts.
+    #[default]
+    GalleryHash,
+}
+
+impl ImageHostStrategy {
+    pub fn parse(value: &str) -> Option<ImageHostStrategy> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "fixed" => Some(ImageHostStrategy::Fixed),
+            "round-robin" | "round_robin" | "roundrobin" => Some(ImageHostStrategy::RoundRobin),
+            "hash" | "gallery-hash" | "per-gallery" => Some(ImageHostStrategy::GalleryHash),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct ImageHosts {
+    /// Origins such as `https://i3.nhentai.net`; empty keeps the URLs as parsed.
+    pub hosts: Vec<String>,
+    pub strategy: ImageHostStrategy,
+}
+
+impl ImageHosts {
+    /// Parses a comma or whitespace separated host list (`i1.nhentai.net, i2.nhentai.net`).
+    pub fn parse(hosts: &str, strategy: ImageHostStrategy) -> ImageHosts {
+        let hosts = hosts
+            .split(|ch: char| ch == ',' || ch.is_whitespace())
+            .map(|host| host.trim().trim_end_matches('/'))
+            .filter(|host| !host.is_empty())
+            .map(|host| {
+                if host.starts_with("https://") || host.starts_with("http://") {
+                    host.to_string()
+                } else {
+                    format!("https://{host}")
+                }
+            })
+            .collect();
+
+        ImageHosts { hosts, strategy }
+    }
+
+    /// Rewrites the page image URLs of `gallery` onto the configured hosts. A page whose
+    /// host `is_up` rejects moves to the next host in line; `is_up` gets the host and the
+    /// rewritten page URL to probe. When no host is up, the preferred one is kept.
+    pub fn apply(&self, gallery: &mut NhentaiGallery, mut is_up: impl FnMut(&str, &str) -> bool) {
+        if self.hosts.is_empty() {
+            return;
+        }
+
+        let gallery_key = gallery.id.clone().unwrap_or_else(|| gallery.title.clone());
+        for (idx, url) in gallery.images.iter_mut().enumerate() {
+            let candidates: Vec<(&str, String)> = (0..self.hosts.len())
+                .filter_map(|failover| {
+                    let host = self.host_for(&gallery_key, idx, failover);
+                    rewrite_image_host(url, host).map(|rewritten| (host, rewritten))
+                })
+                .collect();
+            let Some((_, preferred)) = candidates.first() else {
+                continue;
+            };
+            let chosen = candidates
+                .iter()
+                .find(|(host, rewritten)| is_up(host, rewritten))
+                .map_or(preferred, |(_, rewritten)| rewritten);
+            *url = chosen.clone();
+        }
+    }
+
+    fn host_for(&self, gallery_key: &str, page_idx: usize, failover: usize) -> &str {
+        let idx = match self.strategy {
+            ImageHostStrategy::Fixed => 0,
+            ImageHostStrategy::RoundRobin => page_idx,
+            ImageHostStrategy::GalleryHash => fnv1a(gallery_key) as usize,
+        };
+        &self.hosts[idx.wrapping_add(failover) % self.hosts.len()]
+    }
+}
+
+/// Swaps the origin of an `i*.nhentai.net/galleries/...` URL; other URLs (covers,
+/// thumbnails, mirrors) are left alone.
+fn rewrite_image_host(url: &str, host: &str) -> Option<String> {
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))?;
+    let (current_host, path) = rest.split_once('/')?;
+
+    let shard = current_host
+        .strip_suffix(".nhentai.net")?
+        .strip_prefix('i')?;
+    if !shard.chars().all(|ch| ch.is_ascii_digit()) || !path.starts_with("galleries/") {
+        return None;
+    }
+
+    Some(format!("{host}/{path}"))
+}
+
+fn fnv1a(value: &str) -> u64 {
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in value.bytes() {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gallery() -> NhentaiGallery {
+        NhentaiGallery {
+            id: Some("12345".to_string()),
+            cover_url: "https://t.nhentai.net/galleries/555/cover.jpg".to_string(),
+            images: vec![
+                "https://i.nhentai.net/galleries/555/1.jpg".to_string(),
+                "https://i.nhentai.net/galleries/555/2.jpg".to_string(),
+                "https://i.nhentai.net/galleries/555/3.jpg".to_string(),
+            ],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn parse_normalizes_hosts() {
+        let hosts = ImageHosts::parse(
+            "i1.nhentai.net, https://i2.nhentai.net/",
+            ImageHostStrategy::Fixed,
+        );
+        assert_eq!(
+            hosts.hosts,
+            vec![
+                "https://i1.nhentai.net".to_string(),
+                "https://i2.nhentai.net".to_string()
+            ]
+        );
+    }
+
+    #[test]
+    fn round_robin_rotates_pages() {
+        let hosts = ImageHosts::parse(
+            "i1.nhentai.net,i2.nhentai.net",
+            ImageHostStrategy::RoundRobin,
+        );
+        let mut gallery = gallery();
+        hosts.apply(&mut gallery, |_, _| true);
+        assert_eq!(
+            gallery.images,
+            vec![
+                "https://i1.nhentai.net/galleries/555/1.jpg".to_string(),
+                "https://i2.nhentai.net/galleries/555/2.jpg".to_string(),
+                "https://i1.nhentai.net/galleries/555/3.jpg".to_string(),
+            ]
+        );
+        assert_eq!(
+            gallery.cover_url,
+            "https://t.nhentai.net/galleries/555/cover.jpg"
+        );
+    }
+
+    #[test]
+    fn gallery_hash_pins_one_host_per_gallery() {
+        let hosts = ImageHosts::parse(
+            "i1.nhentai.net i2.nhentai.net i3.nhentai.net",
+            ImageHostStrategy::GalleryHash,
+        );
+        let mut gallery = gallery();
+        hosts.apply(&mut gallery, |_, _| true);
+        let host = gallery.images[0]
+            .split("/galleries/")
+            .next()
+            .unwrap()
+            .to_string();
+        assert!(hosts.hosts.contains(&host));
+        assert!(gallery.images.iter().all(|url| url.starts_with(&host)));
+    }
+
+    #[test]
+    fn failover_moves_to_next_host() {
+        let hosts = ImageHosts::parse("i1.nhentai.net,i2.nhentai.net", ImageHostStrategy::Fixed);
+        let mut gallery = gallery();
+        hosts.apply(&mut gallery, |host, _| host != "https://i1.nhentai.net");
+        assert!(gallery
+            .images
+            .iter()
+            .all(|url| url.starts_with("https://i2.nhentai.net/")));
+    }
+
+    #[test]
+    fn failover_checks_every_round_robin_host() {
+        let hosts = ImageHosts::parse(
+            "i1.nhentai.net,i2.nhentai.net,i3.nhentai.net",
+            ImageHostStrategy::RoundRobin,
+        );
+        let mut gallery = gallery();
+        let mut probed = Vec::new();
+        hosts.apply(&mut gallery, |host, _| {
+            probed.push(host.to_string());
+            host != "https://i2.nhentai.net"
+        });
+        assert_eq!(
+            gallery.images,
+            vec![
+                "https://i1.nhentai.net/galleries/555/1.jpg".to_string(),
+                "https://i3.nhentai.net/galleries/555/2.jpg".to_string(),
+                "https://i3.nhentai.net/galleries/555/3.jpg".to_string(),
+            ]
+        );
+        assert!(probed.contains(&"https://i2.nhentai.net".to_string()));
+    }
+
+    #[test]
+    fn keeps_preferred_host_when_all_are_down() {
+        let hosts = ImageHosts::parse("i1.nhentai.net,i2.nhentai.net", ImageHostStrategy::Fixed);
+        let mut gallery = gallery();
+        hosts.apply(&mut gallery, |_, _| false);
+        assert!(gallery
+            .images
+            .iter()
+            .all(|url| url.starts_with("https://i1.nhentai.net/")));
+    }
+
+    #[test]
+    fn leaves_non_cdn_urls_untouched() {
+        assert_eq!(
+            rewrite_image_host(
+                "https://t3.nhentai.net/galleries/1/thumb.jpg",
+                "https://i1.nhentai.net"
+            ),
+            None
+        );
+        assert_eq!(
+            rewrite_image_host(
+                "https://mirror.example/galleries/1/1.jpg",
+                "https://i1.nhentai.net"
+            ),
+            None
+        );
+    }
+}
@@ -0,0 +1,81 @@
+/// nhentai tag ids that are stable and common enough to resolve without fetching the
+/// gallery: `(id, tag section, name)`.
+/// Every gallery has exactly one category, so all of nhentai's categories are listed.
+const KNOWN_TAGS: [(u64, &str, &str); 13] = [
+    (6346, "language", "japanese"),
+    (12227, "language", "english"),
+    (17249, "language", "translated"),
+    (29963, "language", "chinese"),
+    (33172, "category", "doujinshi"),
+    (33173, "category", "manga"),
+    (33645, "category", "gamecg"),
+    (34065, "category", "non-h"),
+    (34125, "category", "western"),
+    (35447, "category", "imageset"),
+    (36320, "category", "artistcg"),
+    (36487, "category", "cosplay"),
+    (97506, "category", "misc"),
+];
+
+/// Section (`language`/`category`) and name of a well-known tag id.
+pub fn known_tag(id: u64) -> Option<(&'static str, &'static str)> {
+    KNOWN_TAGS
+        .iter()
+        .find(|(known, _, _)| *known == id)
+        .map(|(_, label, name)| (*label, *name))
+}
+
+/// Reads the space-separated ids of a search card's `data-tags` attribute.
+pub fn parse_data_tags(value: &str) -> Vec<u64> {
+    let mut ids: Vec<u64> = Vec::new();
+    for id in value.split_whitespace().filter_map(|id| id.parse().ok()) {
+        if !ids.contains(&id) {
+            ids.push(id);
+        }
+    }
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_data_tags_skips_junk_and_duplicates() {
+        assert_eq!(
+            parse_data_tags(" 12227 abc 33173 12227 "),
+            vec![12227, 33173]
+        );
+        assert!(parse_data_tags("").is_empty());
+    }
+
+    #[test]
+    fn known_tag_resolves_languages_and_categories() {
+        assert_eq!(known_tag(12227), Some(("language", "english")));
+        assert_eq!(known_tag(33172), Some(("category", "doujinshi")));
+        assert_eq!(known_tag(36320), Some(("category", "artistcg")));
+        assert_eq!(known_tag(1), None);
+    }
+
+    #[test]
+    fn known_tags_cover_every_category() {
+        let categories: Vec<&str> = KNOWN_TAGS
+            .iter()
+            .filter(|(_, label, _)| *label == "category")
+            .map(|(_, _, name)| *name)
+            .collect();
+        for name in [
+            "doujinshi",
+            "manga",
+            "artistcg",
+            "gamecg",
+            "western",
+            "non-h",
+            "imageset",
+            "cosplay",
+            "misc",
+        ] {
+            assert!(categories.contains(&name), "missing category {name}");
+        }
+    }
+}
@@ -0,0 +1,155 @@
+/// Which `language:` terms are added to search queries.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LanguageFilter {
+    /// Galleries in any of these languages (nhentai names, e.g. `english`, `japanese`).
+    Only(Vec<String>),
+    Any,
+    /// Use the language named in the book title (`[Chinese]`, `[英訳]`...), any language
+    /// when the title has none.
+    BookHint,
+}
+
+impl Default for LanguageFilter {
+    fn default() -> Self {
+        LanguageFilter::Only(vec!["english".to_string()])
+    }
+}
+
+impl LanguageFilter {
+    /// Parses `any`, `hint` or a list of languages separated by commas, `|` or `OR`.
+    pub fn parse(value: &str) -> Option<LanguageFilter> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "" => None,
+            "any" | "all" | "*" => Some(LanguageFilter::Any),
+            "hint" | "book" | "follow-book" => Some(LanguageFilter::BookHint),
+            list => {
+                let languages: Vec<String> = list
+                    .split([',', '|'])
+                    .flat_map(|part| part.split(" or "))
+                    .map(|lang| lang.trim().trim_start_matches("language:").trim())
+                    .filter(|lang| !lang.is_empty())
+                    .map(|lang| lang.replace(' ', "-"))
+                    .collect();
+                if languages.is_empty() {
+                    None
+                } else {
+                    Some(LanguageFilter::Only(languages))
+                }
+            }
+        }
+    }
+
+    /// Languages to filter `search` on. A query that already has a `language:` term is
+    /// a per-lookup override and gets no extra terms.
+    pub fn languages_for(&self, search: &str) -> Vec<String> {
+        if has_language_term(search) {
+            return vec![];
+        }
+        match self {
+            LanguageFilter::Only(languages) => languages.clone(),
+            LanguageFilter::Any => vec![],
+            LanguageFilter::BookHint => title_language_hint(search)
+                .map(|lang| vec![lang.to_string()])
+                .unwrap_or_default(),
+        }
+    }
+}
+
+fn has_language_term(search: &str) -> bool {
+    search.split_whitespace().any(|term| {
+        term.trim_start_matches('-')
+            .to_ascii_lowercase()
+            .starts_with("language:")
+    })
+}
+
+/// Reads the scanlation language marker nhentai titles carry in brackets.
+pub fn title_language_hint(title: &str) -> Option<&'static str> {
+    bracketed(title).find_map(language_marker)
+}
+
+/// Language named by one bracketed title segment (`English`, `中国翻訳`...).
+pub fn language_marker(content: &str) -> Option<&'static str> {
+    const HINTS: [(&str, &str); 14] = [
+        ("english", "english"),
+        ("eng", "english"),
+        ("英訳", "english"),
+        ("japanese", "japanese"),
+        ("日本語", "japanese"),
+        ("chinese", "chinese"),
+        ("中国翻訳", "chinese"),
+        ("中国語", "chinese"),
+        ("中文", "chinese"),
+        ("korean", "korean"),
+        ("韓国翻訳", "korean"),
+        ("spanish", "spanish"),
+        ("french", "french"),
+        ("russian", "russian"),
+    ];
+
+    let content = content.trim().to_lowercase();
+    HINTS
+        .iter()
+        .find(|(marker, _)| content == *marker)
+        .map(|(_, lang)| *lang)
+}
+
+fn bracketed(title: &str) -> impl Iterator<Item = &str> {
+    title
+        .split(['[', '(', '【'])
+        .skip(1)
+        .filter_map(|part| part.split([']', ')', '】']).next())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_reads_modes_and_language_lists() {
+        assert_eq!(LanguageFilter::parse(" Any "), Some(LanguageFilter::Any));
+        assert_eq!(
+            LanguageFilter::parse("hint"),
+            Some(LanguageFilter::BookHint)
+        );
+        assert_eq!(
+            LanguageFilter::parse("Japanese, chinese | language:korean OR english"),
+            Some(LanguageFilter::Only(vec![
+                "japanese".to_string(),
+                "chinese".to_string(),
+                "korean".to_string(),
+                "english".to_string(),
+            ]))
+        );
+        assert_eq!(LanguageFilter::parse(" , "), None);
+    }
+
+    #[test]
+    fn default_keeps_english_only() {
+        assert_eq!(
+            LanguageFilter::default().languages_for("soft"),
+            vec!["english".to_string()]
+        );
+    }
+
+    #[test]
+    fn query_language_term_overrides_setting() {
+        let filter = LanguageFilter::Only(vec!["english".to_string()]);
+        assert!(filter.languages_for("soft language:japanese").is_empty());
+        assert!(filter.languages_for("soft -language:chinese").is_empty());
+    }
+
+    #[test]
+    fn book_hint_uses_bracketed_title_language() {
+        let filter = LanguageFilter::BookHint;
+        assert_eq!(
+            filter.languages_for("[Circle] Some Title [Chinese] [Digital]"),
+            vec!["chinese".to_string()]
+        );
+        assert_eq!(
+            filter.languages_for("(C99) [サークル] タイトル [中国翻訳]"),
+            vec!["chinese".to_string()]
+        );
+        assert!(filter.languages_for("Some Title").is_empty());
+    }
+}
@@ -1,5 +1,5 @@
-use extism_pdk::{http, log, plugin_fn, FnResult, HttpRequest, Json, LogLevel, WithReturnCode};
-use std::collections::HashSet;
+use extism_pdk::{log, plugin_fn, FnResult, HttpRequest, Json, LogLevel, WithReturnCode};
+use std::collections::{HashMap, HashSet};
 
 use rs_plugin_common_interfaces::{
     domain::external_images::ExternalImage,
@@ -9,21 +9,59 @@ use rs_plugin_common_interfaces::{
         RsLookupSourceResult, RsLookupWrapper,
     },
     request::{RsGroupDownload, RsRequest},
-    CustomParam, CustomParamTypes, PluginInformation, PluginType,
+    CredentialType, CustomParam, CustomParamTypes, PluginInformation, PluginType,
 };
 
+mod api;
+mod blacklist;
+mod cache;
+mod challenge;
 mod convert;
+mod date;
+mod image_hosts;
+mod known_tags;
+mod language;
 mod nhentai;
-
+mod query;
+mod ranking;
+mod retry;
+mod session;
+mod settings;
+mod title_info;
+mod transport;
+
+use api::{
+    api_next_page, build_api_gallery_url, build_api_search_url, build_api_tagged_url,
+    find_api_tag_id, parse_api_gallery_json, parse_api_search_json,
+};
+use blacklist::{Blacklist, BLACKLISTED_ERROR_CODE};
+use cache::{cache_key, DEFAULT_CACHE_MAX_ENTRIES, DEFAULT_CACHE_TTL_CALLS};
+use challenge::{is_challenge_page, BLOCKED_ERROR_CODE};
 use convert::{nhentai_gallery_to_images, nhentai_gallery_to_result};
 use nhentai::{
-    build_gallery_url, build_search_url, parse_gallery_html, parse_lookup_gallery_id,
-    parse_relation_search_term, parse_search_html, parse_search_next_page, NhentaiGallery,
+    build_browse_url, build_favorites_url, build_gallery_url, build_random_url,
+    build_relation_listing_url, build_search_url, is_login_page, is_random_lookup,
+    parse_browse_html, parse_browse_mode, parse_favorites_filter, parse_gallery_html,
+    parse_lookup_gallery_id, parse_page_gallery_id, parse_relation_key, parse_search_html,
+    parse_search_pagination, split_sort_term, BrowseMode, NhentaiGallery, RelationKey,
+    SearchPagination, SearchSort, DEFAULT_BASE_URL,
 };
+use ranking::{is_near_exact, rank_by_title, search_text};
+use retry::{parse_retry_after, retry_delay_ms, should_retry, wait_ms, CAN_WAIT};
+use settings::{Backend, Settings, DEFAULT_MAX_RETRIES, DEFAULT_RANDOM_REROLLS};
+use transport::Transport;
 
 enum LookupTarget {
     DirectGallery(String),
     Search(String),
+    /// The listing page of one artist, tag, group, parody...
+    Relation(RelationKey),
+    /// The logged-in user's favorites, optionally narrowed by a query.
+    Favorites(String),
+    /// `nhentai:popular` / `nhentai:recent` discovery listings.
+    Browse(BrowseMode),
+    /// `nhentai:random`
+    Random,
 }
 
 #[plugin_fn]
@@ -36,18 +74,127 @@ pub fn infos() -> FnResult<Json<PluginInformation>> {
         repo: Some("https://github.com/flashthepublic/plugin-nhentai".to_string()),
         publisher: "neckaros".into(),
         description: "Look up books metadata from nhentai.net".into(),
-        credential_kind: None,
-        settings: vec![CustomParam {
-            name: "custom_search_params".into(),
-            param: CustomParamTypes::Text(None),
-            description: Some("Custom parameters appended to every search query".into()),
-            required: false,
-        }],
+        credential_kind: Some(CredentialType::Token),
+        settings: vec![
+            CustomParam {
+                name: "custom_search_params".into(),
+                param: CustomParamTypes::Text(None),
+                description: Some("Custom parameters appended to every search query".into()),
+                required: false,
+            },
+            CustomParam {
+                name: "blacklist".into(),
+                param: CustomParamTypes::Text(None),
+                description: Some(
+                    "Comma separated tags never imported, e.g. netorare, big breasts, artist:someone. Excluded from searches and filtered out of results; a direct id lookup of a blacklisted gallery fails"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "language".into(),
+                param: CustomParamTypes::Text(Some("english".into())),
+                description: Some(
+                    "Search languages: one or more joined with commas (matched with OR), any, or hint (use the [Language] tag in the book title). A language: term in the query overrides it"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "sort".into(),
+                param: CustomParamTypes::Text(Some("recent".into())),
+                description: Some(
+                    "Search order: recent, popular-today, popular-week, popular-month or popular. A sort:<order> term in the query overrides it"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "backend".into(),
+                param: CustomParamTypes::Text(Some("html".into())),
+                description: Some(
+                    "Data source: html (scrape pages), api (JSON endpoints) or auto (API first, HTML when it fails or finds nothing)"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "base_url".into(),
+                param: CustomParamTypes::Url(Some(DEFAULT_BASE_URL.into())),
+                description: Some(
+                    "Site origin to query, e.g. a mirror or a local test server".into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "image_hosts".into(),
+                param: CustomParamTypes::Text(None),
+                description: Some(
+                    "Comma separated image CDN hosts, e.g. i1.nhentai.net,i2.nhentai.net (empty keeps the page URLs). Each host in use is checked once per lookup and pages on a host that is down move to the next one"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "image_host_strategy".into(),
+                param: CustomParamTypes::Text(Some("hash".into())),
+                description: Some(
+                    "How pages are spread over image_hosts: fixed, round-robin or hash (one host per gallery)"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "max_retries".into(),
+                param: CustomParamTypes::UInteger(Some(DEFAULT_MAX_RETRIES.into())),
+                description: Some(
+                    "Retries on rate limiting (429) or server errors, with backoff in native builds only. The wasm plugin cannot sleep, so it retries server errors and failed requests at once and returns rate limiting (429) or a Retry-After wait as an error"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "enrich_results".into(),
+                param: CustomParamTypes::UInteger(Some(0)),
+                description: Some(
+                    "Fetch the gallery page of the first N search hits for full tags, artists and pages (0 keeps the search cards)"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "random_rerolls".into(),
+                param: CustomParamTypes::UInteger(Some(DEFAULT_RANDOM_REROLLS.into())),
+                description: Some(
+                    "How many more random galleries nhentai:random draws when one hits the blacklist or language filter"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "cache_ttl".into(),
+                param: CustomParamTypes::UInteger(Some(DEFAULT_CACHE_TTL_CALLS)),
+                description: Some(
+                    "How many plugin calls a fetched page is reused before nhentai is queried again (0 disables the cache)"
+                        .into(),
+                ),
+                required: false,
+            },
+            CustomParam {
+                name: "cache_max_entries".into(),
+                param: CustomParamTypes::UInteger(Some(DEFAULT_CACHE_MAX_ENTRIES as u64)),
+                description: Some("Maximum number of pages kept in the response cache".into()),
+                required: false,
+            },
+        ],
         ..Default::default()
     }))
 }
 
-fn build_http_request(url: String) -> HttpRequest {
+/// Fallback User-Agent when the credential does not carry the browser's own.
+const DEFAULT_USER_AGENT: &str = "rs-plugin-nh/0.1 (+https://nhentai.net)";
+
+fn build_http_request(url: String, accept: &str, settings: &Settings) -> HttpRequest {
     let mut request = HttpRequest {
         url,
         headers: Default::default(),
@@ -56,93 +203,659 @@ fn build_http_request(url: String) -> HttpRequest {
 
     request
         .headers
-        .insert("Accept".to_string(), "text/html".to_string());
-    request.headers.insert(
-        "User-Agent".to_string(),
-        "rs-plugin-nh/0.1 (+https://nhentai.net)".to_string(),
-    );
+        .insert("Accept".to_string(), accept.to_string());
+    request
+        .headers
+        .insert("User-Agent".to_string(), user_agent(settings).to_string());
+    if let Some(cookie) = settings.session.as_ref().and_then(|s| s.cookie.as_ref()) {
+        request.headers.insert("Cookie".to_string(), cookie.clone());
+    }
 
     request
 }
 
+fn user_agent(settings: &Settings) -> &str {
+    settings
+        .session
+        .as_ref()
+        .and_then(|s| s.user_agent.as_deref())
+        .unwrap_or(DEFAULT_USER_AGENT)
+}
+
 fn execute_search_request(
     search: &str,
     page: Option<u32>,
-    custom_search_params: Option<&str>,
-) -> FnResult<(Vec<NhentaiGallery>, Option<String>)> {
-    let url = build_search_url(search, page, custom_search_params)
-        .ok_or_else(|| WithReturnCode::new(extism_pdk::Error::msg("Not supported"), 404))?;
-
-    let body = execute_html_request(url)?;
-    let galleries = parse_search_html(&body);
-    let current_page = page.unwrap_or(1);
-    let next_page_key = if galleries.is_empty() {
-        None
-    } else {
-        parse_search_next_page(&body, current_page).map(|p| p.to_string())
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    let (search, sort) = split_sort_term(search);
+    let sort = sort.unwrap_or(settings.sort);
+    let search = search.as_str();
+    match settings.backend {
+        Backend::Html => execute_html_search_request(search, page, sort, settings),
+        Backend::Api => execute_api_search_request(search, page, sort, settings),
+        Backend::Auto => api_then_html(
+            "search",
+            || execute_api_search_request(search, page, sort, settings),
+            || execute_html_search_request(search, page, sort, settings),
+        ),
+    }
+}
+
+/// The `auto` backend: the API result, or the HTML one when the API fails or finds nothing.
+fn api_then_html(
+    what: &str,
+    api: impl FnOnce() -> FnResult<(Vec<NhentaiGallery>, SearchPagination)>,
+    html: impl FnOnce() -> FnResult<(Vec<NhentaiGallery>, SearchPagination)>,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    match api() {
+        Ok((galleries, pagination)) if !galleries.is_empty() => Ok((galleries, pagination)),
+        Ok(_) => {
+            log!(
+                LogLevel::Info,
+                "nhentai API {} found nothing, trying HTML",
+                what
+            );
+            html()
+        }
+        Err(e) => {
+            log!(
+                LogLevel::Warn,
+                "nhentai API {} failed, falling back to HTML: {}",
+                what,
+                e.0
+            );
+            html()
+        }
+    }
+}
+
+fn execute_html_search_request(
+    search: &str,
+    page: Option<u32>,
+    sort: SearchSort,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    let url = build_search_url(
+        &settings.base_url,
+        search,
+        page,
+        sort,
+        &settings.language.languages_for(search),
+        settings.custom_search_params.as_deref(),
+        &settings.blacklist,
+    )
+    .ok_or_else(|| WithReturnCode::new(extism_pdk::Error::msg("Not supported"), 404))?;
+
+    let body = execute_html_request(url, settings)?;
+    let galleries = parse_search_html(&body, &settings.base_url);
+    let pagination = search_page_pagination(&galleries, &body, page);
+    Ok((galleries, pagination))
+}
+
+fn execute_api_search_request(
+    search: &str,
+    page: Option<u32>,
+    sort: SearchSort,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    let url = build_api_search_url(
+        &settings.base_url,
+        search,
+        page,
+        sort,
+        &settings.language.languages_for(search),
+        settings.custom_search_params.as_deref(),
+        &settings.blacklist,
+    )
+    .ok_or_else(|| WithReturnCode::new(extism_pdk::Error::msg("Not supported"), 404))?;
+
+    let body = execute_json_request(url, settings)?;
+    api_search_page(&body, page, settings)
+}
+
+/// Galleries and pagination of a search or tagged API response.
+fn api_search_page(
+    body: &str,
+    page: Option<u32>,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    let result = parse_api_search_json(body, &settings.base_url).ok_or_else(|| {
+        WithReturnCode::new(
+            extism_pdk::Error::msg("Invalid nhentai API search response"),
+            502,
+        )
+    })?;
+    let pagination = SearchPagination {
+        next_page: api_next_page(&result, page.unwrap_or(1)),
+    };
+    Ok((result.galleries, pagination))
+}
+
+/// Pagination of an HTML result page; an empty page never points further.
+fn search_page_pagination(
+    galleries: &[NhentaiGallery],
+    body: &str,
+    page: Option<u32>,
+) -> SearchPagination {
+    let mut pagination = parse_search_pagination(body, page.unwrap_or(1));
+    if galleries.is_empty() {
+        pagination.next_page = None;
+    }
+    pagination
+}
+
+fn execute_relation_request(
+    key: &RelationKey,
+    page: Option<u32>,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    match settings.backend {
+        Backend::Html => execute_html_relation_request(key, page, settings),
+        Backend::Api => execute_api_relation_request(key, page, settings),
+        Backend::Auto => api_then_html(
+            "relation listing",
+            || execute_api_relation_request(key, page, settings),
+            || execute_html_relation_request(key, page, settings),
+        ),
+    }
+}
+
+/// `/api/galleries/tagged` takes a numeric tag id, so a search for the relation finds the
+/// id first. A relation no gallery carries yields an empty page.
+fn execute_api_relation_request(
+    key: &RelationKey,
+    page: Option<u32>,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    let search_url = build_api_search_url(
+        &settings.base_url,
+        &key.search_term(),
+        None,
+        SearchSort::Recent,
+        &[],
+        None,
+        &Blacklist::default(),
+    )
+    .ok_or_else(|| WithReturnCode::new(extism_pdk::Error::msg("Not supported"), 404))?;
+    let body = execute_json_request(search_url, settings)?;
+    let Some(tag_id) = find_api_tag_id(&body, key) else {
+        log!(
+            LogLevel::Info,
+            "nhentai API has no {} {}",
+            key.namespace,
+            key.slug
+        );
+        return Ok((vec![], SearchPagination::default()));
+    };
+
+    let url = build_api_tagged_url(&settings.base_url, tag_id, page, settings.sort);
+    let body = execute_json_request(url, settings)?;
+    api_search_page(&body, page, settings)
+}
+
+/// The relation's HTML listing page. When the listing is missing (renamed slug, mirror
+/// without listings) it falls back to a search.
+fn execute_html_relation_request(
+    key: &RelationKey,
+    page: Option<u32>,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    let url = build_relation_listing_url(&settings.base_url, key, page, settings.sort);
+    let body = match execute_html_request(url, settings) {
+        Ok(body) => body,
+        Err(e) if e.1 == 404 => {
+            log!(
+                LogLevel::Warn,
+                "nhentai {} listing not found, searching instead",
+                key.namespace
+            );
+            return execute_search_request(&key.search_term(), page, settings);
+        }
+        Err(e) => return Err(e),
     };
-    Ok((galleries, next_page_key))
+
+    let galleries = parse_search_html(&body, &settings.base_url);
+    let pagination = search_page_pagination(&galleries, &body, page);
+    Ok((galleries, pagination))
 }
 
-fn execute_gallery_request(gallery_id: &str) -> FnResult<Vec<NhentaiGallery>> {
-    let body = execute_html_request(build_gallery_url(gallery_id))?;
-    Ok(parse_gallery_html(&body, gallery_id).into_iter().collect())
+/// The homepage listings only exist as HTML, so this ignores the backend setting.
+fn execute_browse_request(
+    mode: BrowseMode,
+    page: Option<u32>,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    let url = build_browse_url(&settings.base_url, mode, page);
+    let body = execute_html_request(url, settings)?;
+    let galleries = parse_browse_html(&body, mode, &settings.base_url);
+    let pagination = match mode {
+        BrowseMode::Popular => SearchPagination::default(),
+        BrowseMode::Recent => search_page_pagination(&galleries, &body, page),
+    };
+    Ok((galleries, pagination))
 }
 
-fn execute_html_request(url: String) -> FnResult<String> {
-    let request = build_http_request(url);
-    let res = http::request::<Vec<u8>>(&request, None);
+/// `/random/` redirects to a gallery page; the host follows the redirect, so the id is read
+/// from the page itself. Galleries failing the blacklist or language filter are redrawn up
+/// to `random_rerolls` times. HTML only, whatever the backend.
+fn execute_random_request(settings: &Settings) -> FnResult<Vec<NhentaiGallery>> {
+    let url = build_random_url(&settings.base_url);
+    let languages = settings.language.languages_for("");
+    for _ in 0..=settings.random_rerolls {
+        // Never cached: every draw has to reach the site.
+        let body = send_request(&url, "text/html", settings)?;
+        let gallery_id = parse_page_gallery_id(&body, &settings.base_url).ok_or_else(|| {
+            WithReturnCode::new(
+                extism_pdk::Error::msg("nhentai /random/ did not lead to a gallery page"),
+                502,
+            )
+        })?;
+        let Some(gallery) = parse_gallery_html(&body, &gallery_id, &settings.base_url) else {
+            continue;
+        };
 
-    match res {
-        Ok(res) if res.status_code() >= 200 && res.status_code() < 300 => {
-            Ok(String::from_utf8_lossy(&res.body()).to_string())
+        if let Some(entry) = settings.blacklist.matches(&gallery) {
+            log!(
+                LogLevel::Info,
+                "nhentai random gallery {} rerolled ({})",
+                gallery_id,
+                entry.describe()
+            );
+            continue;
         }
-        Ok(res) => {
+        if !languages.is_empty() && !gallery.languages.iter().any(|l| languages.contains(l)) {
             log!(
-                LogLevel::Error,
-                "nhentai HTTP error {}: {}",
-                res.status_code(),
-                String::from_utf8_lossy(&res.body())
+                LogLevel::Info,
+                "nhentai random gallery {} rerolled (language {:?})",
+                gallery_id,
+                gallery.languages
             );
-            Err(WithReturnCode::new(
-                extism_pdk::Error::msg(format!("HTTP error: {}", res.status_code())),
-                res.status_code() as i32,
-            ))
+            continue;
         }
-        Err(e) => {
-            log!(LogLevel::Error, "nhentai request failed: {}", e);
-            Err(WithReturnCode(e, 500))
+        return Ok(vec![gallery]);
+    }
+
+    Err(WithReturnCode::new(
+        extism_pdk::Error::msg(format!(
+            "No random nhentai gallery passed the filters in {} draws",
+            settings.random_rerolls.saturating_add(1)
+        )),
+        404,
+    ))
+}
+
+/// Favorites only exist as HTML pages, so this ignores the backend setting.
+fn execute_favorites_request(
+    filter: &str,
+    page: Option<u32>,
+    settings: &Settings,
+) -> FnResult<(Vec<NhentaiGallery>, SearchPagination)> {
+    if settings
+        .session
+        .as_ref()
+        .and_then(|s| s.cookie.as_ref())
+        .is_none()
+    {
+        return Err(WithReturnCode::new(
+            extism_pdk::Error::msg("nhentai favorites require a session cookie credential"),
+            401,
+        ));
+    }
+
+    // Favorites belong to the session, so they are never cached where another cookie
+    // could be served them.
+    let url = build_favorites_url(&settings.base_url, filter, page);
+    let body = send_request(&url, "text/html", settings)?;
+    if is_login_page(&body) {
+        return Err(WithReturnCode::new(
+            extism_pdk::Error::msg("nhentai session is not logged in (expired session cookie?)"),
+            401,
+        ));
+    }
+
+    let galleries = parse_search_html(&body, &settings.base_url);
+    let pagination = search_page_pagination(&galleries, &body, page);
+    Ok((galleries, pagination))
+}
+
+/// Replaces the first `limit` search/listing cards with their fully parsed galleries, so
+/// they carry tags, people and pages. Cards whose gallery fails to load are kept as they are.
+fn enrich_galleries(
+    cards: Vec<NhentaiGallery>,
+    limit: usize,
+    settings: &Settings,
+) -> Vec<NhentaiGallery> {
+    cards
+        .into_iter()
+        .enumerate()
+        .map(|(idx, card)| {
+            // Galleries with a page count were already parsed in full (e.g. random draws).
+            let Some(id) = card
+                .id
+                .clone()
+                .filter(|_| idx < limit && card.pages.is_none())
+            else {
+                return card;
+            };
+            match execute_gallery_request(&id, settings) {
+                Ok(galleries) => galleries.into_iter().next().unwrap_or(card),
+                Err(e) => {
+                    log!(
+                        LogLevel::Warn,
+                        "nhentai failed to load gallery {}: {}",
+                        id,
+                        e.0
+                    );
+                    card
+                }
+            }
+        })
+        .collect()
+}
+
+fn execute_gallery_request(gallery_id: &str, settings: &Settings) -> FnResult<Vec<NhentaiGallery>> {
+    match settings.backend {
+        Backend::Html => execute_html_gallery_request(gallery_id, settings),
+        Backend::Api => execute_api_gallery_request(gallery_id, settings),
+        Backend::Auto => match execute_api_gallery_request(gallery_id, settings) {
+            Ok(galleries) if !galleries.is_empty() => Ok(galleries),
+            Ok(_) => execute_html_gallery_request(gallery_id, settings),
+            Err(e) => {
+                log!(
+                    LogLevel::Warn,
+                    "nhentai API gallery {} failed, falling back to HTML: {}",
+                    gallery_id,
+                    e.0
+                );
+                execute_html_gallery_request(gallery_id, settings)
+            }
+        },
+    }
+}
+
+/// Gallery fetch for direct-id lookups: an unknown id (404) yields no galleries so the
+/// caller can fall back to a name search, while rate limits and outages are surfaced. A
+/// blacklisted gallery is refused with `BLACKLISTED_ERROR_CODE` rather than returned or
+/// skipped.
+fn execute_direct_gallery_request(
+    gallery_id: &str,
+    settings: &Settings,
+) -> FnResult<Vec<NhentaiGallery>> {
+    let galleries = match execute_gallery_request(gallery_id, settings) {
+        Err(e) if e.1 == 404 => return Ok(vec![]),
+        result => result?,
+    };
+    if let Some(entry) = galleries
+        .iter()
+        .find_map(|gallery| settings.blacklist.matches(gallery))
+    {
+        log!(
+            LogLevel::Info,
+            "nhentai gallery {} is blacklisted ({})",
+            gallery_id,
+            entry.describe()
+        );
+        return Err(WithReturnCode::new(
+            extism_pdk::Error::msg(format!(
+                "nhentai gallery {gallery_id} has blacklisted {}",
+                entry.describe()
+            )),
+            BLACKLISTED_ERROR_CODE,
+        ));
+    }
+    Ok(galleries)
+}
+
+/// Drops galleries carrying a blacklisted tag. Searches already exclude them in the query;
+/// this catches listings, favorites and anything the site let through.
+fn without_blacklisted(galleries: Vec<NhentaiGallery>, settings: &Settings) -> Vec<NhentaiGallery> {
+    if settings.blacklist.is_empty() {
+        return galleries;
+    }
+    galleries
+        .into_iter()
+        .filter(|gallery| match settings.blacklist.matches(gallery) {
+            Some(entry) => {
+                log!(
+                    LogLevel::Info,
+                    "nhentai filtered gallery {} ({})",
+                    gallery.id.as_deref().unwrap_or(&gallery.gallery_url),
+                    entry.describe()
+                );
+                false
+            }
+            None => true,
+        })
+        .collect()
+}
+
+fn execute_html_gallery_request(
+    gallery_id: &str,
+    settings: &Settings,
+) -> FnResult<Vec<NhentaiGallery>> {
+    let url = build_gallery_url(&settings.base_url, gallery_id);
+    let body = execute_html_request(url, settings)?;
+    Ok(parse_gallery_html(&body, gallery_id, &settings.base_url)
+        .into_iter()
+        .collect())
+}
+
+fn execute_api_gallery_request(
+    gallery_id: &str,
+    settings: &Settings,
+) -> FnResult<Vec<NhentaiGallery>> {
+    let url = build_api_gallery_url(&settings.base_url, gallery_id);
+    let body = execute_json_request(url, settings)?;
+    let gallery = parse_api_gallery_json(&body, &settings.base_url).ok_or_else(|| {
+        WithReturnCode::new(
+            extism_pdk::Error::msg("Invalid nhentai API gallery response"),
+            502,
+        )
+    })?;
+    Ok(vec![gallery])
+}
+
+fn execute_html_request(url: String, settings: &Settings) -> FnResult<String> {
+    execute_request(url, "text/html", settings)
+}
+
+fn execute_json_request(url: String, settings: &Settings) -> FnResult<String> {
+    execute_request(url, "application/json", settings)
+}
+
+fn execute_request(url: String, accept: &str, settings: &Settings) -> FnResult<String> {
+    let cookie = settings.session.as_ref().and_then(|s| s.cookie.as_deref());
+    let key = cache_key(&url, cookie);
+    if let Some(body) = settings.cache.get(&key) {
+        return Ok(body);
+    }
+    let body = send_request(&url, accept, settings)?;
+    // A login page answers with 200 too; caching it would outlive a fixed cookie.
+    if !is_login_page(&body) {
+        settings.cache.put(&key, &body);
+    }
+    Ok(body)
+}
+
+/// Fetches `url` with retries, bypassing the response cache.
+fn send_request(url: &str, accept: &str, settings: &Settings) -> FnResult<String> {
+    let request = build_http_request(url.to_string(), accept, settings);
+    let max_attempts = settings.max_retries.saturating_add(1);
+    let mut attempt = 0;
+
+    loop {
+        attempt += 1;
+        log!(
+            LogLevel::Debug,
+            "nhentai GET {} (attempt {}/{})",
+            request.url,
+            attempt,
+            max_attempts
+        );
+
+        let retry_after = match settings.transport.send(&request) {
+            Ok(res) => {
+                let status = res.status;
+                let body = res.body;
+                if is_challenge_page(status, &res.headers, &body) {
+                    log!(
+                        LogLevel::Error,
+                        "nhentai served a bot challenge for {} (HTTP {})",
+                        request.url,
+                        status
+                    );
+                    return Err(WithReturnCode::new(
+                        extism_pdk::Error::msg(
+                            "Blocked by nhentai bot protection (Cloudflare challenge)",
+                        ),
+                        BLOCKED_ERROR_CODE,
+                    ));
+                }
+                if (200..300).contains(&status) {
+                    return Ok(body);
+                }
+
+                log!(
+                    LogLevel::Error,
+                    "nhentai HTTP error {} (attempt {}/{}): {}",
+                    status,
+                    attempt,
+                    max_attempts,
+                    body
+                );
+                let retry_after = parse_retry_after(&res.headers);
+                if !should_retry(status, retry_after) || attempt >= max_attempts {
+                    return Err(WithReturnCode::new(
+                        extism_pdk::Error::msg(format!("HTTP error: {status}")),
+                        status as i32,
+                    ));
+                }
+                retry_after
+            }
+            Err(e) => {
+                log!(
+                    LogLevel::Error,
+                    "nhentai request failed (attempt {}/{}): {}",
+                    attempt,
+                    max_attempts,
+                    e
+                );
+                if attempt >= max_attempts {
+                    return Err(WithReturnCode(e, 500));
+                }
+                None
+            }
+        };
+
+        if CAN_WAIT {
+            let delay = retry_delay_ms(attempt, retry_after, &request.url);
+            log!(
+                LogLevel::Warn,
+                "nhentai retrying {} in {}ms",
+                request.url,
+                delay
+            );
+            wait_ms(delay);
+        } else {
+            log!(LogLevel::Warn, "nhentai retrying {}", request.url);
         }
     }
 }
 
-fn lookup_galleries(
+fn load_settings(lookup: &RsLookupWrapper) -> FnResult<Settings> {
+    let mut settings = Settings::from_lookup(lookup);
+    settings.transport = Transport::from_config().map_err(|e| WithReturnCode::new(e, 400))?;
+    settings.cache.begin_call();
+    Ok(settings)
+}
+
+/// A result gallery with how confidently it matches the lookup.
+type LookupHit = (NhentaiGallery, Option<RsLookupMatchType>);
+
+fn lookup_galleries(lookup: &RsLookupWrapper) -> FnResult<(Vec<LookupHit>, SearchPagination)> {
+    let settings = load_settings(lookup)?;
+    let (galleries, pagination, match_type) = find_galleries(lookup, &settings)?;
+    if match_type == Some(RsLookupMatchType::ExactId) {
+        let galleries = with_image_hosts(galleries, &settings);
+        return Ok((
+            galleries
+                .into_iter()
+                .map(|g| (g, match_type.clone()))
+                .collect(),
+            pagination,
+        ));
+    }
+
+    // Ranked before enrichment so the best matches are the ones fetched in full.
+    let rank_name = ranking_name(lookup, &settings);
+    let galleries = match rank_name.as_deref() {
+        Some(name) => rank_by_title(galleries, name),
+        None => galleries,
+    };
+    let galleries = without_blacklisted(
+        enrich_galleries(galleries, settings.enrich_limit, &settings),
+        &settings,
+    );
+    // Ranked again now that the enriched hits carry favorites and alternate titles.
+    let galleries = match rank_name.as_deref() {
+        Some(name) => rank_by_title(galleries, name),
+        None => galleries,
+    };
+    // Only the best near-exact hit is marked, so automatic matching can take it safely.
+    let mut exact_found = false;
+    let hits = with_image_hosts(galleries, &settings)
+        .into_iter()
+        .map(|g| {
+            let near_exact = !exact_found
+                && rank_name
+                    .as_deref()
+                    .is_some_and(|name| is_near_exact(&g, name));
+            exact_found |= near_exact;
+            (g, near_exact.then_some(RsLookupMatchType::ExactText))
+        })
+        .collect();
+    Ok((hits, pagination))
+}
+
+/// Name that free-text search hits are ranked against (within one results page). Relation
+/// and favorites listings keep the site's order.
+fn ranking_name(lookup: &RsLookupWrapper, settings: &Settings) -> Option<String> {
+    let RsLookupQuery::Book(book) = &lookup.query else {
+        return None;
+    };
+    let search = match resolve_book_lookup_target(book, settings)? {
+        LookupTarget::Search(search) => search,
+        // Only reached here when the id was unknown and the name was searched instead.
+        LookupTarget::DirectGallery(_) => book.name.clone()?,
+        _ => return None,
+    };
+    Some(search_text(&search)).filter(|text| !text.is_empty())
+}
+
+fn find_galleries(
     lookup: &RsLookupWrapper,
-) -> FnResult<(Vec<NhentaiGallery>, Option<String>, Option<RsLookupMatchType>)> {
+    settings: &Settings,
+) -> FnResult<(
+    Vec<NhentaiGallery>,
+    SearchPagination,
+    Option<RsLookupMatchType>,
+)> {
     let book = match &lookup.query {
         RsLookupQuery::Book(book) => book,
-        _ => return Ok((vec![], None, None)),
+        _ => return Ok((vec![], SearchPagination::default(), None)),
     };
 
-    let custom_search_params = lookup
-        .params
-        .as_ref()
-        .and_then(|p| p.get("custom_search_params"))
-        .and_then(|s| match s {
-            CustomParamTypes::Text(v) => v.as_deref(),
-            _ => None,
-        });
-
-    let page = book
-        .page_key
-        .as_deref()
-        .and_then(|k| k.parse::<u32>().ok());
+    let page = book.page_key.as_deref().and_then(|k| k.parse::<u32>().ok());
 
-    match resolve_book_lookup_target(book) {
+    match resolve_book_lookup_target(book, settings) {
         Some(LookupTarget::DirectGallery(gallery_id)) => {
-            let galleries = execute_gallery_request(&gallery_id).unwrap_or_default();
+            let galleries = execute_direct_gallery_request(&gallery_id, settings)?;
             if !galleries.is_empty() {
-                return Ok((galleries, None, Some(RsLookupMatchType::ExactId)));
+                return Ok((
+                    galleries,
+                    SearchPagination::default(),
+                    Some(RsLookupMatchType::ExactId),
+                ));
             }
             // Gallery lookup returned nothing; fall back to name search if available.
             match book
@@ -152,18 +865,33 @@ fn lookup_galleries(
                 .filter(|n| !n.is_empty())
             {
                 Some(name) => {
-                    let (galleries, next_page_key) =
-                        execute_search_request(name, page, custom_search_params)?;
-                    Ok((galleries, next_page_key, None))
+                    let (galleries, pagination) = execute_search_request(name, page, settings)?;
+                    Ok((galleries, pagination, None))
                 }
-                None => Ok((vec![], None, None)),
+                None => Ok((vec![], SearchPagination::default(), None)),
             }
         }
         Some(LookupTarget::Search(search)) => {
-            let (galleries, next_page_key) =
-                execute_search_request(&search, page, custom_search_params)?;
-            Ok((galleries, next_page_key, None))
+            let (galleries, pagination) = execute_search_request(&search, page, settings)?;
+            Ok((galleries, pagination, None))
+        }
+        Some(LookupTarget::Relation(key)) => {
+            let (galleries, pagination) = execute_relation_request(&key, page, settings)?;
+            Ok((galleries, pagination, None))
         }
+        Some(LookupTarget::Favorites(filter)) => {
+            let (galleries, pagination) = execute_favorites_request(&filter, page, settings)?;
+            Ok((galleries, pagination, None))
+        }
+        Some(LookupTarget::Browse(mode)) => {
+            let (galleries, pagination) = execute_browse_request(mode, page, settings)?;
+            Ok((galleries, pagination, None))
+        }
+        Some(LookupTarget::Random) => Ok((
+            execute_random_request(settings)?,
+            SearchPagination::default(),
+            None,
+        )),
         _ => Err(WithReturnCode::new(
             extism_pdk::Error::msg("Not supported"),
             404,
@@ -171,41 +899,67 @@ fn lookup_galleries(
     }
 }
 
-fn resolve_book_lookup_target(book: &RsLookupBook) -> Option<LookupTarget> {
-    if let Some(id) = book.name.as_deref().and_then(parse_lookup_gallery_id) {
+fn resolve_book_lookup_target(book: &RsLookupBook, settings: &Settings) -> Option<LookupTarget> {
+    let gallery_id = |value: &str| parse_lookup_gallery_id(value, &settings.base_url);
+
+    if let Some(id) = book.name.as_deref().and_then(gallery_id) {
         return Some(LookupTarget::DirectGallery(id));
     }
 
     if let Some(ids) = book.ids.as_ref() {
-        if let Some(id) = ids.redseat().and_then(parse_lookup_gallery_id) {
+        if let Some(id) = ids.redseat().and_then(gallery_id) {
             return Some(LookupTarget::DirectGallery(id));
         }
 
-        if let Some(id) = ids.slug().and_then(parse_lookup_gallery_id) {
+        if let Some(id) = ids.slug().and_then(gallery_id) {
             return Some(LookupTarget::DirectGallery(id));
         }
 
-        if let Some(id) = ids.as_all_ids().iter().find_map(|value| parse_lookup_gallery_id(value)) {
+        if let Some(id) = ids.as_all_ids().iter().find_map(|value| gallery_id(value)) {
             return Some(LookupTarget::DirectGallery(id));
         }
     }
 
-    // Check for relation IDs (e.g. "nhentai-group:maiju") and convert to search terms.
-    if let Some(term) = book.name.as_deref().and_then(parse_relation_search_term) {
-        return Some(LookupTarget::Search(term));
+    if let Some(mode) = book.name.as_deref().and_then(parse_browse_mode) {
+        return Some(LookupTarget::Browse(mode));
+    }
+
+    if book.name.as_deref().is_some_and(is_random_lookup) {
+        return Some(LookupTarget::Random);
+    }
+
+    if let Some(filter) = book.name.as_deref().and_then(parse_favorites_filter) {
+        return Some(LookupTarget::Favorites(filter));
+    }
+
+    if let Some(filter) = book.ids.as_ref().and_then(|ids| {
+        ids.as_all_ids()
+            .iter()
+            .find_map(|value| parse_favorites_filter(value))
+    }) {
+        return Some(LookupTarget::Favorites(filter));
+    }
+
+    // Relation IDs (e.g. "nhentai-group:maiju") list that relation's galleries.
+    if let Some(key) = book.name.as_deref().and_then(parse_relation_key) {
+        return Some(LookupTarget::Relation(key));
     }
 
     if let Some(ids) = book.ids.as_ref() {
-        if let Some(term) = ids.redseat().and_then(parse_relation_search_term) {
-            return Some(LookupTarget::Search(term));
+        if let Some(key) = ids.redseat().and_then(parse_relation_key) {
+            return Some(LookupTarget::Relation(key));
         }
 
-        if let Some(term) = ids.slug().and_then(parse_relation_search_term) {
-            return Some(LookupTarget::Search(term));
+        if let Some(key) = ids.slug().and_then(parse_relation_key) {
+            return Some(LookupTarget::Relation(key));
         }
 
-        if let Some(term) = ids.as_all_ids().iter().find_map(|value| parse_relation_search_term(value)) {
-            return Some(LookupTarget::Search(term));
+        if let Some(key) = ids
+            .as_all_ids()
+            .iter()
+            .find_map(|value| parse_relation_key(value))
+        {
+            return Some(LookupTarget::Relation(key));
         }
     }
 
@@ -220,20 +974,20 @@ fn resolve_book_lookup_target(book: &RsLookupBook) -> Option<LookupTarget> {
 pub fn lookup_metadata(
     Json(lookup): Json<RsLookupWrapper>,
 ) -> FnResult<Json<RsLookupMetadataResults>> {
-    let (galleries, next_page_key, match_type) = lookup_galleries(&lookup)?;
+    let (hits, pagination) = lookup_galleries(&lookup)?;
 
-    let results = galleries
+    let results = hits
         .into_iter()
-        .map(|g| {
+        .map(|(g, match_type)| {
             let mut result = nhentai_gallery_to_result(g);
-            result.match_type = match_type.clone();
+            result.match_type = match_type;
             result
         })
         .collect();
 
     Ok(Json(RsLookupMetadataResults {
         results,
-        next_page_key,
+        next_page_key: pagination.next_page_key(),
     }))
 }
 
@@ -241,14 +995,15 @@ pub fn lookup_metadata(
 pub fn lookup_metadata_images(
     Json(lookup): Json<RsLookupWrapper>,
 ) -> FnResult<Json<Vec<ExternalImage>>> {
-    let (galleries, _, match_type) = lookup_galleries(&lookup)?;
+    let (hits, _) = lookup_galleries(&lookup)?;
 
-    let images: Vec<ExternalImage> = galleries
+    let images: Vec<ExternalImage> = hits
         .iter()
-        .flat_map(nhentai_gallery_to_images)
-        .map(|mut img| {
-            img.match_type = match_type.clone();
-            img
+        .flat_map(|(g, match_type)| {
+            nhentai_gallery_to_images(g).into_iter().map(|mut img| {
+                img.match_type = match_type.clone();
+                img
+            })
         })
         .collect();
 
@@ -257,49 +1012,57 @@ pub fn lookup_metadata_images(
 
 #[plugin_fn]
 pub fn lookup(Json(lookup): Json<RsLookupWrapper>) -> FnResult<Json<RsLookupSourceResult>> {
-    let book = match &lookup.query {
-        RsLookupQuery::Book(book) => book,
-        _ => return Ok(Json(RsLookupSourceResult::NotApplicable)),
+    let RsLookupQuery::Book(book) = &lookup.query else {
+        return Ok(Json(RsLookupSourceResult::NotApplicable));
     };
+    if resolve_book_lookup_target(book, &Settings::from_lookup(&lookup)).is_none() {
+        return Ok(Json(RsLookupSourceResult::NotApplicable));
+    }
 
-    let custom_search_params = lookup
-        .params
-        .as_ref()
-        .and_then(|p| p.get("custom_search_params"))
-        .and_then(|s| match s {
-            CustomParamTypes::Text(v) => v.as_deref(),
-            _ => None,
-        });
+    // Same galleries, ranking and match types as `lookup_metadata`, as downloads.
+    let (hits, _) = lookup_galleries(&lookup)?;
+    Ok(Json(hits_to_group_result(hits)))
+}
 
-    match resolve_book_lookup_target(book) {
-        Some(LookupTarget::DirectGallery(gallery_id)) => {
-            let galleries = execute_gallery_request(&gallery_id).unwrap_or_default();
-            if !galleries.is_empty() {
-                return Ok(Json(galleries_to_group_result(
-                    galleries,
-                    Some(RsLookupMatchType::ExactId),
-                )));
-            }
-            // Fall back to name search if the gallery returned nothing.
-            match book
-                .name
-                .as_deref()
-                .map(str::trim)
-                .filter(|n| !n.is_empty())
-            {
-                Some(name) => {
-                    let (galleries, _) = execute_search_request(name, None, custom_search_params)?;
-                    Ok(Json(galleries_to_group_result(galleries, None)))
-                }
-                None => Ok(Json(RsLookupSourceResult::NotFound)),
-            }
-        }
-        Some(LookupTarget::Search(search)) => {
-            let (galleries, _) = execute_search_request(&search, None, custom_search_params)?;
-            Ok(Json(galleries_to_group_result(galleries, None)))
-        }
-        _ => Ok(Json(RsLookupSourceResult::NotApplicable)),
+/// Moves page images onto the configured CDN hosts. Every host a page lands on is probed
+/// once per call and pages on a host that is down fail over to the next one.
+fn with_image_hosts(galleries: Vec<NhentaiGallery>, settings: &Settings) -> Vec<NhentaiGallery> {
+    let hosts = &settings.image_hosts;
+    if hosts.hosts.is_empty() {
+        return galleries;
     }
+
+    let mut health: HashMap<String, bool> = HashMap::new();
+    galleries
+        .into_iter()
+        .map(|mut gallery| {
+            hosts.apply(&mut gallery, |host, url| {
+                *health.entry(host.to_string()).or_insert_with(|| {
+                    let up = probe_image(url, settings);
+                    if !up {
+                        log!(LogLevel::Warn, "nhentai image host unavailable: {}", host);
+                    }
+                    up
+                })
+            });
+            gallery
+        })
+        .collect()
+}
+
+fn probe_image(url: &str, settings: &Settings) -> bool {
+    let mut request = HttpRequest {
+        url: url.to_string(),
+        headers: Default::default(),
+        method: Some("HEAD".into()),
+    };
+    request
+        .headers
+        .insert("User-Agent".to_string(), user_agent(settings).to_string());
+    matches!(
+        settings.transport.send(&request),
+        Ok(res) if (200..300).contains(&res.status)
+    )
 }
 
 fn gallery_to_group_download(
@@ -312,7 +1075,10 @@ fn gallery_to_group_download(
         .map(|url| RsRequest {
             url: url.clone(),
             permanent: true,
-            mime: url.split('.').last().map(|ext| format!("image/{}", ext)),
+            mime: url
+                .split('.')
+                .next_back()
+                .map(|ext| format!("image/{}", ext)),
             instant: Some(true),
             ..Default::default()
         })
@@ -465,16 +1231,13 @@ fn relation_details_to_series_lookup_names(values: &[nhentai::NhentaiRelation])
         .collect()
 }
 
-fn galleries_to_group_result(
-    galleries: Vec<NhentaiGallery>,
-    match_type: Option<RsLookupMatchType>,
-) -> RsLookupSourceResult {
-    if galleries.is_empty() {
+fn hits_to_group_result(hits: Vec<LookupHit>) -> RsLookupSourceResult {
+    if hits.is_empty() {
         return RsLookupSourceResult::NotFound;
     }
-    let group_downloads = galleries
+    let group_downloads = hits
         .into_iter()
-        .map(|g| gallery_to_group_download(g, match_type.clone()))
+        .map(|(g, match_type)| gallery_to_group_download(g, match_type))
         .collect();
     RsLookupSourceResult::GroupRequest(group_downloads)
 }
@@ -505,9 +1268,9 @@ mod tests {
             params: None,
         };
 
-        let (galleries, _, match_type) = lookup_galleries(&lookup).expect("lookup should succeed");
-        assert!(galleries.is_empty());
-        assert!(match_type.is_none());
+        let (hits, pagination) = lookup_galleries(&lookup).expect("lookup should succeed");
+        assert!(hits.is_empty());
+        assert_eq!(pagination, SearchPagination::default());
     }
 
     #[test]
@@ -534,7 +1297,7 @@ mod tests {
             page_key: None,
         };
 
-        let target = resolve_book_lookup_target(&book);
+        let target = resolve_book_lookup_target(&book, &Settings::default());
         match target {
             Some(LookupTarget::DirectGallery(id)) => assert_eq!(id, "12345"),
             _ => panic!("Expected direct gallery target"),
@@ -549,7 +1312,7 @@ mod tests {
             page_key: None,
         };
 
-        let target = resolve_book_lookup_target(&book);
+        let target = resolve_book_lookup_target(&book, &Settings::default());
         match target {
             Some(LookupTarget::DirectGallery(id)) => assert_eq!(id, "67890"),
             _ => panic!("Expected direct gallery target from ids"),
@@ -557,13 +1320,13 @@ mod tests {
     }
 
     #[test]
-    fn galleries_to_group_result_empty_returns_not_found() {
-        let result = galleries_to_group_result(vec![], None);
+    fn hits_to_group_result_empty_returns_not_found() {
+        let result = hits_to_group_result(vec![]);
         assert!(matches!(result, RsLookupSourceResult::NotFound));
     }
 
     #[test]
-    fn galleries_to_group_result_maps_each_gallery() {
+    fn hits_to_group_result_maps_each_gallery() {
         let galleries = vec![
             NhentaiGallery {
                 id: Some("1".to_string()),
@@ -584,7 +1347,12 @@ mod tests {
             },
         ];
 
-        let result = galleries_to_group_result(galleries, Some(RsLookupMatchType::ExactId));
+        let result = hits_to_group_result(
+            galleries
+                .into_iter()
+                .map(|g| (g, Some(RsLookupMatchType::ExactId)))
+                .collect(),
+        );
         let RsLookupSourceResult::GroupRequest(downloads) = result else {
             panic!("Expected GroupRequest");
         };
@@ -639,19 +1407,23 @@ mod tests {
             people_details: vec![nhentai::NhentaiRelation {
                 id: "nhentai-artist:bai-asuka".to_string(),
                 name: "bai asuka".to_string(),
+                count: None,
             }],
             tag_details: vec![nhentai::NhentaiRelation {
                 id: "nhentai-tags:full-color".to_string(),
                 name: "full color".to_string(),
+                count: None,
             }],
             parody_details: vec![
                 nhentai::NhentaiRelation {
                     id: "nhentai-parody:naruto".to_string(),
                     name: "naruto".to_string(),
+                    count: None,
                 },
                 nhentai::NhentaiRelation {
                     id: "nhentai-parody:original".to_string(),
                     name: "original".to_string(),
+                    count: None,
                 },
             ],
             ..Default::default()
@@ -693,10 +1465,64 @@ mod tests {
             page_key: None,
         };
 
-        let target = resolve_book_lookup_target(&book);
+        let target = resolve_book_lookup_target(&book, &Settings::default());
+        match target {
+            Some(LookupTarget::Relation(key)) => {
+                assert_eq!((key.namespace, key.slug.as_str()), ("group", "maiju"))
+            }
+            _ => panic!("Expected Relation target for relation ID in name"),
+        }
+    }
+
+    #[test]
+    fn resolve_target_favorites_in_name() {
+        let book = RsLookupBook {
+            name: Some("nhentai-favorites".to_string()),
+            ids: None,
+            page_key: Some("2".to_string()),
+        };
+
+        let target = resolve_book_lookup_target(&book, &Settings::default());
+        match target {
+            Some(LookupTarget::Favorites(filter)) => assert!(filter.is_empty()),
+            _ => panic!("Expected Favorites target for nhentai-favorites"),
+        }
+    }
+
+    #[test]
+    fn resolve_target_browse_and_random_names() {
+        let book = |name: &str| RsLookupBook {
+            name: Some(name.to_string()),
+            ids: None,
+            page_key: None,
+        };
+
+        assert!(matches!(
+            resolve_book_lookup_target(&book("nhentai:popular"), &Settings::default()),
+            Some(LookupTarget::Browse(BrowseMode::Popular))
+        ));
+        assert!(matches!(
+            resolve_book_lookup_target(&book("NHentai:Recent"), &Settings::default()),
+            Some(LookupTarget::Browse(BrowseMode::Recent))
+        ));
+        assert!(matches!(
+            resolve_book_lookup_target(&book("nhentai:random"), &Settings::default()),
+            Some(LookupTarget::Random)
+        ));
+    }
+
+    #[test]
+    fn resolve_target_favorites_query_in_other_ids() {
+        let book = RsLookupBook {
+            name: Some("some book name".to_string()),
+            ids: Some(RsIds::try_from(vec!["nhentai-favorites:full color".to_string()]).unwrap()),
+            page_key: None,
+        };
+
+        let target = resolve_book_lookup_target(&book, &Settings::default());
         match target {
-            Some(LookupTarget::Search(term)) => assert_eq!(term, "group:maiju"),
-            _ => panic!("Expected Search target for relation ID in name"),
+            Some(LookupTarget::Favorites(filter)) => assert_eq!(filter, "full color"),
+            _ => panic!("Expected Favorites target in other_ids"),
         }
     }
 
@@ -708,10 +1534,15 @@ mod tests {
             page_key: None,
         };
 
-        let target = resolve_book_lookup_target(&book);
+        let target = resolve_book_lookup_target(&book, &Settings::default());
         match target {
-            Some(LookupTarget::Search(term)) => assert_eq!(term, "artist:sasaki-musashi"),
-            _ => panic!("Expected Search target for relation ID in other_ids"),
+            Some(LookupTarget::Relation(key)) => {
+                assert_eq!(
+                    (key.namespace, key.slug.as_str()),
+                    ("artist", "sasaki-musashi")
+                )
+            }
+            _ => panic!("Expected Relation target for relation ID in other_ids"),
         }
     }
 
@@ -723,7 +1554,7 @@ mod tests {
             page_key: None,
         };
 
-        let target = resolve_book_lookup_target(&book);
+        let target = resolve_book_lookup_target(&book, &Settings::default());
         match target {
             Some(LookupTarget::DirectGallery(id)) => assert_eq!(id, "12345"),
             _ => panic!("Expected DirectGallery to win over relation ID"),
@@ -738,10 +1569,12 @@ mod tests {
             page_key: None,
         };
 
-        let target = resolve_book_lookup_target(&book);
+        let target = resolve_book_lookup_target(&book, &Settings::default());
         match target {
-            Some(LookupTarget::Search(term)) => assert_eq!(term, "tag:full-color"),
-            _ => panic!("Expected Search target with tag: prefix"),
+            Some(LookupTarget::Relation(key)) => {
+                assert_eq!(key.search_term(), r#"tag:"full-color""#)
+            }
+            _ => panic!("Expected Relation target with tag namespace"),
         }
     }
 
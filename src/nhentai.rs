@@ -1,10 +1,18 @@
 use regex::Regex;
 use scraper::{ElementRef, Html, Selector};
 
+use crate::blacklist::Blacklist;
+use crate::date::parse_datetime;
+use crate::known_tags::{known_tag, parse_data_tags};
+use crate::query::SearchQuery;
+use crate::title_info::{parse_title_info, TitleInfo};
+
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
 pub struct NhentaiRelation {
     pub id: String,
     pub name: String,
+    /// Galleries carrying the tag on the site (its `span.count`).
+    pub count: Option<u64>,
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Default)]
@@ -27,26 +35,149 @@ pub struct NhentaiGallery {
     pub people_details: Vec<NhentaiRelation>,
     pub tag_details: Vec<NhentaiRelation>,
     pub parody_details: Vec<NhentaiRelation>,
+    /// nhentai's numeric tag ids (`data-tags` on search cards, `id` in API tags).
+    pub site_tag_ids: Vec<u64>,
+    /// Event, circle and markers read from the brackets `clean_title` strips.
+    pub title_info: TitleInfo,
+    /// Other cleaned titles of the gallery (pretty, Japanese...), without `title` itself.
+    pub alternate_titles: Vec<String>,
+    /// Cleaned original Japanese title, when the gallery has one.
+    pub japanese_title: Option<String>,
+    /// When the gallery was uploaded, in unix seconds.
+    pub upload_date: Option<i64>,
+    /// How many users favorited the gallery.
+    pub favorites: Option<u32>,
+}
+
+/// Raw (not `clean_title`d) titles of a gallery: nhentai's English, "pretty" and Japanese
+/// titles, as in `h1.title`, `span.pretty` and `h2.title` or the API's `title` object.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct GalleryTitles {
+    pub english: Option<String>,
+    pub pretty: Option<String>,
+    pub japanese: Option<String>,
+}
+
+impl GalleryTitles {
+    /// The title the gallery is named after: the first one that is more than brackets.
+    pub fn primary(&self) -> &str {
+        [&self.english, &self.pretty, &self.japanese]
+            .into_iter()
+            .flatten()
+            .find(|title| !clean_title(title).is_empty())
+            .map(String::as_str)
+            .unwrap_or_default()
+    }
+
+    /// Distinct cleaned titles other than `title`, compared case-insensitively.
+    pub fn alternates(&self, title: &str) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for raw in [&self.english, &self.pretty, &self.japanese]
+            .into_iter()
+            .flatten()
+        {
+            let cleaned = clean_title(raw);
+            let seen = cleaned.to_lowercase() == title.to_lowercase()
+                || out
+                    .iter()
+                    .any(|t| t.to_lowercase() == cleaned.to_lowercase());
+            if !cleaned.is_empty() && !seen {
+                out.push(cleaned);
+            }
+        }
+        out
+    }
+}
+
+pub const DEFAULT_BASE_URL: &str = "https://nhentai.net";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SearchSort {
+    /// nhentai's default order, newest uploads first.
+    #[default]
+    Recent,
+    PopularToday,
+    PopularWeek,
+    PopularMonth,
+    Popular,
 }
 
-pub fn build_search_url(search: &str, page: Option<u32>, custom_search_params: Option<&str>) -> Option<String> {
+impl SearchSort {
+    pub fn parse(value: &str) -> Option<SearchSort> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "recent" | "date" | "new" => Some(SearchSort::Recent),
+            "popular-today" | "today" => Some(SearchSort::PopularToday),
+            "popular-week" | "week" => Some(SearchSort::PopularWeek),
+            "popular-month" | "month" => Some(SearchSort::PopularMonth),
+            "popular" | "popular-all" | "all-time" => Some(SearchSort::Popular),
+            _ => None,
+        }
+    }
+
+    /// Value of the `sort=` parameter; recent is the site default and sends none.
+    pub fn as_param(self) -> Option<&'static str> {
+        match self {
+            SearchSort::Recent => None,
+            SearchSort::PopularToday => Some("popular-today"),
+            SearchSort::PopularWeek => Some("popular-week"),
+            SearchSort::PopularMonth => Some("popular-month"),
+            SearchSort::Popular => Some("popular"),
+        }
+    }
+}
+
+/// Pulls a `sort:<order>` override out of a search query. The query keeps the term
+/// between pages, so every page of a lookup uses the same order.
+pub fn split_sort_term(search: &str) -> (String, Option<SearchSort>) {
+    let mut sort = None;
+    let terms: Vec<&str> = search
+        .split_whitespace()
+        .filter(|term| {
+            let parsed = term
+                .get(..5)
+                .filter(|prefix| prefix.eq_ignore_ascii_case("sort:"))
+                .and_then(|_| SearchSort::parse(&term[5..]));
+            if parsed.is_some() {
+                sort = parsed;
+            }
+            parsed.is_none()
+        })
+        .collect();
+    (terms.join(" "), sort)
+}
+
+pub fn build_search_query(
+    search: &str,
+    languages: &[String],
+    custom_search_params: Option<&str>,
+    blacklist: &Blacklist,
+) -> Option<String> {
     let trimmed = search.trim();
     if trimmed.is_empty() {
         return None;
     }
 
-    let mut query = format!("language:english {trimmed}");
-    if let Some(params) = custom_search_params {
-        let params = params.trim();
-        if !params.is_empty() {
-            query.push(' ');
-            query.push_str(params);
-        }
+    let query = SearchQuery::new()
+        .include_any("language", languages)
+        .extend(SearchQuery::parse(trimmed))
+        .extend(SearchQuery::parse(custom_search_params.unwrap_or_default()));
+    Some(blacklist.exclude_from(query).render())
+}
+
+pub fn build_search_url(
+    base_url: &str,
+    search: &str,
+    page: Option<u32>,
+    sort: SearchSort,
+    languages: &[String],
+    custom_search_params: Option<&str>,
+    blacklist: &Blacklist,
+) -> Option<String> {
+    let query = build_search_query(search, languages, custom_search_params, blacklist)?;
+    let mut url = format!("{base_url}/search/?q={}", encode_query_component(&query));
+    if let Some(sort) = sort.as_param() {
+        url.push_str(&format!("&sort={sort}"));
     }
-    let mut url = format!(
-        "https://nhentai.net/search/?q={}",
-        encode_query_component(&query)
-    );
     if let Some(p) = page {
         if p > 1 {
             url.push_str(&format!("&page={p}"));
@@ -55,32 +186,134 @@ pub fn build_search_url(search: &str, page: Option<u32>, custom_search_params: O
     Some(url)
 }
 
-pub fn parse_search_next_page(html: &str, current_page: u32) -> Option<u32> {
+/// Paging state of a search or listing page.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SearchPagination {
+    pub next_page: Option<u32>,
+}
+
+impl SearchPagination {
+    pub fn next_page_key(&self) -> Option<String> {
+        self.next_page.map(|p| p.to_string())
+    }
+}
+
+/// Reads the pagination block (`a.next`, `a.last`, page links).
+pub fn parse_search_pagination(html: &str, current_page: u32) -> SearchPagination {
     let document = Html::parse_document(html);
-    let next_selector = Selector::parse("a.next").ok()?;
-    if document.select(&next_selector).next().is_some() {
+    let link_selector =
+        Selector::parse(".pagination a, a.next, a.last").expect("valid pagination selector");
+
+    let mut has_next = false;
+    let mut last_page = None;
+    let mut highest_page = current_page;
+    for link in document.select(&link_selector) {
+        let classes: Vec<&str> = link.value().classes().collect();
+        let page = link
+            .value()
+            .attr("href")
+            .and_then(page_param)
+            .or_else(|| link.text().collect::<String>().trim().parse().ok());
+        if classes.contains(&"next") {
+            has_next = true;
+        }
+        if classes.contains(&"last") {
+            last_page = page;
+        }
+        if let Some(page) = page {
+            highest_page = highest_page.max(page);
+        }
+    }
+
+    // Without `a.last` the count is only known on the last page, where no `a.next` shows.
+    let total_pages = last_page
+        .or_else(|| (!has_next).then_some(highest_page))
+        .map(|total| total.max(current_page));
+    let next_page = if has_next || total_pages.is_some_and(|total| current_page < total) {
         Some(current_page + 1)
     } else {
         None
+    };
+
+    SearchPagination { next_page }
+}
+
+fn page_param(href: &str) -> Option<u32> {
+    href.split(['?', '&'])
+        .find_map(|pair| pair.strip_prefix("page="))
+        .and_then(|page| page.parse().ok())
+}
+
+pub fn build_gallery_url(base_url: &str, gallery_id: &str) -> String {
+    format!("{base_url}/g/{gallery_id}/")
+}
+
+/// Reads a favorites lookup id: `nhentai-favorites` lists every favorite,
+/// `nhentai-favorites:<query>` only those matching the query.
+pub fn parse_favorites_filter(value: &str) -> Option<String> {
+    let trimmed = value.trim();
+    let prefix = "nhentai-favorites";
+    if trimmed.len() < prefix.len() || !trimmed[..prefix.len()].eq_ignore_ascii_case(prefix) {
+        return None;
+    }
+
+    match &trimmed[prefix.len()..] {
+        "" => Some(String::new()),
+        rest => rest.strip_prefix(':').map(|q| q.trim().to_string()),
+    }
+}
+
+pub fn build_favorites_url(base_url: &str, filter: &str, page: Option<u32>) -> String {
+    let mut params = Vec::new();
+    let filter = filter.trim();
+    if !filter.is_empty() {
+        params.push(format!("q={}", encode_query_component(filter)));
+    }
+    if let Some(p) = page.filter(|p| *p > 1) {
+        params.push(format!("page={p}"));
+    }
+
+    let mut url = format!("{base_url}/favorites/");
+    if !params.is_empty() {
+        url.push('?');
+        url.push_str(&params.join("&"));
     }
+    url
+}
+
+/// Anonymous requests to `/favorites/` are redirected to the login form.
+pub fn is_login_page(html: &str) -> bool {
+    html.contains("name=\"username_or_email\"") || html.contains("action=\"/login/")
+}
+
+/// A relation id such as `nhentai-artist:bai-asuka`, split into the site namespace
+/// (`artist`, `tag`...) and its slug.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RelationKey {
+    pub namespace: &'static str,
+    pub slug: String,
 }
 
-pub fn build_gallery_url(gallery_id: &str) -> String {
-    format!("https://nhentai.net/g/{gallery_id}/")
+impl RelationKey {
+    /// Free-text search equivalent, for when the listing page is unavailable.
+    pub fn search_term(&self) -> String {
+        // The slug is kept as-is: hyphens may be part of the name (`non-h`, `x-eros`).
+        SearchQuery::new()
+            .include(self.namespace, &self.slug)
+            .render()
+    }
 }
 
-pub fn parse_relation_search_term(value: &str) -> Option<String> {
+pub fn parse_relation_key(value: &str) -> Option<RelationKey> {
     let trimmed = value.trim();
-    let without_prefix = trimmed
-        .strip_prefix("nhentai-")
-        .or_else(|| {
-            let lower = trimmed.to_ascii_lowercase();
-            if lower.starts_with("nhentai-") {
-                Some(&trimmed["nhentai-".len()..])
-            } else {
-                None
-            }
-        })?;
+    let without_prefix = trimmed.strip_prefix("nhentai-").or_else(|| {
+        let lower = trimmed.to_ascii_lowercase();
+        if lower.starts_with("nhentai-") {
+            Some(&trimmed["nhentai-".len()..])
+        } else {
+            None
+        }
+    })?;
 
     let (category, slug) = without_prefix.split_once(':')?;
     let slug = slug.trim();
@@ -89,7 +322,7 @@ pub fn parse_relation_search_term(value: &str) -> Option<String> {
     }
 
     let category_lower = category.to_ascii_lowercase();
-    let search_category = match category_lower.as_str() {
+    let namespace = match category_lower.as_str() {
         "tags" => "tag",
         "artist" => "artist",
         "group" => "group",
@@ -100,10 +333,34 @@ pub fn parse_relation_search_term(value: &str) -> Option<String> {
         _ => return None,
     };
 
-    Some(format!("{search_category}:{slug}"))
+    Some(RelationKey {
+        namespace,
+        slug: slug.split_whitespace().collect::<Vec<_>>().join("-"),
+    })
 }
 
-pub fn parse_lookup_gallery_id(value: &str) -> Option<String> {
+/// Listing page of one relation, e.g. `/artist/bai-asuka/` or `/tag/full-color/popular-week`.
+pub fn build_relation_listing_url(
+    base_url: &str,
+    key: &RelationKey,
+    page: Option<u32>,
+    sort: SearchSort,
+) -> String {
+    let mut url = format!(
+        "{base_url}/{}/{}/",
+        key.namespace,
+        encode_path_segment(&key.slug)
+    );
+    if let Some(sort) = sort.as_param() {
+        url.push_str(sort);
+    }
+    if let Some(p) = page.filter(|p| *p > 1) {
+        url.push_str(&format!("?page={p}"));
+    }
+    url
+}
+
+pub fn parse_lookup_gallery_id(value: &str, base_url: &str) -> Option<String> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
         return None;
@@ -116,12 +373,101 @@ pub fn parse_lookup_gallery_id(value: &str) -> Option<String> {
         }
     }
 
-    extract_gallery_id(trimmed)
+    extract_gallery_id(trimmed, base_url)
+}
+
+pub fn parse_search_html(html: &str, base_url: &str) -> Vec<NhentaiGallery> {
+    parse_gallery_cards(&Html::parse_document(html), ".gallery", base_url)
+}
+
+/// Discovery listings that need no book to look up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrowseMode {
+    /// The homepage's "Popular Now" block.
+    Popular,
+    /// New uploads, the paginated homepage listing.
+    Recent,
+}
+
+/// Reads the reserved names `nhentai:popular` and `nhentai:recent`.
+pub fn parse_browse_mode(value: &str) -> Option<BrowseMode> {
+    let lower = value.trim().to_ascii_lowercase();
+    match lower.strip_prefix("nhentai:")? {
+        "popular" | "popular-now" => Some(BrowseMode::Popular),
+        "recent" | "new" | "latest" => Some(BrowseMode::Recent),
+        _ => None,
+    }
+}
+
+/// Both listings live on the homepage; only new uploads have further pages.
+pub fn build_browse_url(base_url: &str, mode: BrowseMode, page: Option<u32>) -> String {
+    match (mode, page.filter(|p| *p > 1)) {
+        (BrowseMode::Recent, Some(p)) => format!("{base_url}/?page={p}"),
+        _ => format!("{base_url}/"),
+    }
+}
+
+/// `nhentai:random` asks for a random gallery.
+pub fn is_random_lookup(value: &str) -> bool {
+    value.trim().eq_ignore_ascii_case("nhentai:random")
+}
+
+pub fn build_random_url(base_url: &str) -> String {
+    format!("{base_url}/random/")
 }
 
-pub fn parse_search_html(html: &str) -> Vec<NhentaiGallery> {
+/// Id of the gallery a page shows, for pages reached through a redirect (`/random/`).
+pub fn parse_page_gallery_id(html: &str, base_url: &str) -> Option<String> {
     let document = Html::parse_document(html);
-    let gallery_selector = Selector::parse(".gallery").expect("valid .gallery selector");
+    let link_selector = Selector::parse("link[rel=\"canonical\"], meta[property=\"og:url\"]")
+        .expect("valid canonical selector");
+    let from_links = document.select(&link_selector).find_map(|el| {
+        let url = el
+            .value()
+            .attr("href")
+            .or_else(|| el.value().attr("content"))?;
+        extract_gallery_id(url, base_url)
+    });
+    if from_links.is_some() {
+        return from_links;
+    }
+
+    let id_selector = Selector::parse("#gallery_id").expect("valid gallery id selector");
+    document
+        .select(&id_selector)
+        .next()
+        .map(|el| {
+            el.text()
+                .collect::<String>()
+                .trim()
+                .trim_start_matches('#')
+                .to_string()
+        })
+        .filter(|id| is_valid_gallery_id(id))
+}
+
+pub fn parse_browse_html(html: &str, mode: BrowseMode, base_url: &str) -> Vec<NhentaiGallery> {
+    let document = Html::parse_document(html);
+    match mode {
+        BrowseMode::Popular => parse_gallery_cards(&document, ".index-popular .gallery", base_url),
+        BrowseMode::Recent => {
+            let recent = parse_gallery_cards(
+                &document,
+                ".index-container:not(.index-popular) .gallery",
+                base_url,
+            );
+            // Later pages and some mirrors have no container classes, only cards.
+            if recent.is_empty() {
+                parse_gallery_cards(&document, ".gallery", base_url)
+            } else {
+                recent
+            }
+        }
+    }
+}
+
+fn parse_gallery_cards(document: &Html, selector: &str, base_url: &str) -> Vec<NhentaiGallery> {
+    let gallery_selector = Selector::parse(selector).expect("valid gallery card selector");
     let caption_selector = Selector::parse(".caption").expect("valid .caption selector");
     let cover_selector = Selector::parse("a.cover").expect("valid a.cover selector");
     let image_selector = Selector::parse("img").expect("valid img selector");
@@ -129,11 +475,12 @@ pub fn parse_search_html(html: &str) -> Vec<NhentaiGallery> {
     let mut items = Vec::new();
 
     for gallery in document.select(&gallery_selector) {
-        let title = gallery
+        let raw_title = gallery
             .select(&caption_selector)
             .next()
-            .map(|el| clean_title(&el.text().collect::<String>()))
+            .map(|el| el.text().collect::<String>())
             .unwrap_or_default();
+        let title = clean_title(&raw_title);
 
         if title.is_empty() {
             continue;
@@ -144,7 +491,7 @@ pub fn parse_search_html(html: &str) -> Vec<NhentaiGallery> {
             .as_ref()
             .and_then(|el| el.value().attr("href"))
             .unwrap_or_default();
-        let gallery_url = normalize_url(href);
+        let gallery_url = normalize_url(href, base_url);
         if gallery_url.is_empty() {
             continue;
         }
@@ -161,19 +508,32 @@ pub fn parse_search_html(html: &str) -> Vec<NhentaiGallery> {
                     .attr("data-src")
                     .or_else(|| el.value().attr("src"))
             })
-            .map(normalize_url)
+            .map(|src| normalize_url(src, base_url))
             .unwrap_or_default();
 
         if cover_url.is_empty() {
             continue;
         }
 
+        let site_tag_ids = gallery
+            .value()
+            .attr("data-tags")
+            .map(parse_data_tags)
+            .unwrap_or_default();
+        let known = known_tag_buckets(&site_tag_ids, base_url);
+
         items.push(NhentaiGallery {
-            id: extract_gallery_id(href),
+            id: extract_gallery_id(href, base_url),
             title,
             cover_url: cover_url.clone(),
             gallery_url,
             images: vec![cover_url],
+            languages: known.languages,
+            categories: known.categories,
+            tag_ids: known.tag_ids,
+            tag_details: known.tag_details,
+            site_tag_ids,
+            title_info: parse_title_info(&raw_title),
             ..Default::default()
         });
     }
@@ -181,18 +541,44 @@ pub fn parse_search_html(html: &str) -> Vec<NhentaiGallery> {
     items
 }
 
-pub fn parse_gallery_html(html: &str, gallery_id: &str) -> Option<NhentaiGallery> {
+pub fn parse_gallery_html(html: &str, gallery_id: &str, base_url: &str) -> Option<NhentaiGallery> {
     let document = Html::parse_document(html);
 
-    let title = parse_gallery_title(&document);
-    let cover_url = parse_gallery_cover_url(&document);
-    let tag_buckets = parse_tag_buckets(&document);
+    let titles = parse_gallery_titles(&document);
+    let cover_url = parse_gallery_cover_url(&document, base_url);
+    let mut tag_buckets = parse_tag_buckets(&document, base_url);
+    if tag_buckets.upload_date.is_none() {
+        tag_buckets.upload_date =
+            parse_script_number(&document, "upload_date").and_then(|date| i64::try_from(date).ok());
+    }
+    tag_buckets.favorites = parse_gallery_favorites(&document).or_else(|| {
+        parse_script_number(&document, "num_favorites").and_then(|n| u32::try_from(n).ok())
+    });
 
     let mut images = parse_script_image_urls(&document).unwrap_or_default();
     if images.is_empty() {
-        images = parse_thumbnail_image_urls(&document);
+        images = parse_thumbnail_image_urls(&document, base_url);
     }
 
+    Some(assemble_gallery(
+        base_url,
+        gallery_id,
+        &titles,
+        cover_url,
+        images,
+        tag_buckets,
+    ))
+}
+
+/// Builds the final gallery from parsed parts, shared by the HTML and JSON backends.
+pub fn assemble_gallery(
+    base_url: &str,
+    gallery_id: &str,
+    titles: &GalleryTitles,
+    cover_url: String,
+    mut images: Vec<String>,
+    tag_buckets: TagBuckets,
+) -> NhentaiGallery {
     if images.is_empty() && !cover_url.is_empty() {
         images.push(cover_url.clone());
     }
@@ -204,6 +590,8 @@ pub fn parse_gallery_html(html: &str, gallery_id: &str) -> Option<NhentaiGallery
         images.first().cloned().unwrap_or_default()
     };
 
+    let raw_title = titles.primary();
+    let title = clean_title(raw_title);
     let title = if title.is_empty() {
         format!("nhentai {gallery_id}")
     } else {
@@ -213,11 +601,17 @@ pub fn parse_gallery_html(html: &str, gallery_id: &str) -> Option<NhentaiGallery
     let image_pages = u32::try_from(images.len()).ok().filter(|count| *count > 0);
     let pages = tag_buckets.pages.or(image_pages);
 
-    Some(NhentaiGallery {
+    NhentaiGallery {
         id: Some(gallery_id.to_string()),
+        alternate_titles: titles.alternates(&title),
+        japanese_title: titles
+            .japanese
+            .as_deref()
+            .map(clean_title)
+            .filter(|t| !t.is_empty()),
         title,
         cover_url: resolved_cover,
-        gallery_url: build_gallery_url(gallery_id),
+        gallery_url: build_gallery_url(base_url, gallery_id),
         images,
         tags: tag_buckets.tags,
         artists: tag_buckets.artists,
@@ -232,25 +626,36 @@ pub fn parse_gallery_html(html: &str, gallery_id: &str) -> Option<NhentaiGallery
         people_details: tag_buckets.people_details,
         tag_details: tag_buckets.tag_details,
         parody_details: tag_buckets.parody_details,
-    })
+        site_tag_ids: tag_buckets.site_tag_ids,
+        upload_date: tag_buckets.upload_date,
+        favorites: tag_buckets.favorites,
+        title_info: parse_title_info(raw_title),
+    }
+}
+
+/// Languages and categories of the well-known ids among `site_tag_ids`.
+fn known_tag_buckets(site_tag_ids: &[u64], base_url: &str) -> TagBuckets {
+    let mut out = TagBuckets::default();
+    for (label, name) in site_tag_ids.iter().filter_map(|id| known_tag(*id)) {
+        let relation = build_relation(label, "", name, base_url);
+        let relation_ids = relation.iter().map(|r| r.id.clone()).collect();
+        out.absorb(
+            label,
+            vec![name.to_string()],
+            relation_ids,
+            relation.into_iter().collect(),
+        );
+    }
+    out
 }
 
-pub fn extract_gallery_id(value: &str) -> Option<String> {
+pub fn extract_gallery_id(value: &str, base_url: &str) -> Option<String> {
     let trimmed = value.trim();
     if trimmed.is_empty() {
         return None;
     }
 
-    let without_scheme = trimmed
-        .strip_prefix("https://")
-        .or_else(|| trimmed.strip_prefix("http://"))
-        .unwrap_or(trimmed);
-
-    let path = without_scheme
-        .strip_prefix("nhentai.net/")
-        .or_else(|| without_scheme.strip_prefix("www.nhentai.net/"))
-        .unwrap_or(without_scheme)
-        .trim_matches('/');
+    let path = strip_site_prefix(trimmed, base_url).trim_matches('/');
 
     let mut parts = path.split('/');
 
@@ -265,45 +670,108 @@ pub fn extract_gallery_id(value: &str) -> Option<String> {
 }
 
 #[derive(Default)]
-struct TagBuckets {
-    tags: Vec<String>,
-    artists: Vec<String>,
-    groups: Vec<String>,
-    parodies: Vec<String>,
-    characters: Vec<String>,
-    languages: Vec<String>,
-    categories: Vec<String>,
-    pages: Option<u32>,
-    people_ids: Vec<String>,
-    tag_ids: Vec<String>,
-    people_details: Vec<NhentaiRelation>,
-    tag_details: Vec<NhentaiRelation>,
-    parody_details: Vec<NhentaiRelation>,
-}
-
-fn parse_gallery_title(document: &Html) -> String {
-    let title_selectors = ["#info h1.title", "h1.title", "meta[property=\"og:title\"]"];
-
-    for selector_str in title_selectors {
-        let selector = Selector::parse(selector_str).expect("valid title selector");
-        if let Some(node) = document.select(&selector).next() {
-            let value = if selector_str.starts_with("meta[") {
-                node.value().attr("content").unwrap_or_default().to_string()
-            } else {
-                node.text().collect::<String>()
-            };
-            let value = value.trim_end_matches(" - nhentai");
-            let cleaned = clean_title(value);
-            if !cleaned.is_empty() {
-                return cleaned;
+pub struct TagBuckets {
+    pub tags: Vec<String>,
+    pub artists: Vec<String>,
+    pub groups: Vec<String>,
+    pub parodies: Vec<String>,
+    pub characters: Vec<String>,
+    pub languages: Vec<String>,
+    pub categories: Vec<String>,
+    pub pages: Option<u32>,
+    pub people_ids: Vec<String>,
+    pub tag_ids: Vec<String>,
+    pub people_details: Vec<NhentaiRelation>,
+    pub tag_details: Vec<NhentaiRelation>,
+    pub parody_details: Vec<NhentaiRelation>,
+    pub site_tag_ids: Vec<u64>,
+    /// Unix seconds of the `Uploaded` row.
+    pub upload_date: Option<i64>,
+    pub favorites: Option<u32>,
+}
+
+impl TagBuckets {
+    /// Routes the values of one tag section (e.g. "artists") into the matching buckets.
+    pub fn absorb(
+        &mut self,
+        label: &str,
+        values: Vec<String>,
+        relation_ids: Vec<String>,
+        relation_details: Vec<NhentaiRelation>,
+    ) {
+        match label {
+            "tags" | "tag" => {
+                push_all_unique(&mut self.tags, values);
+                push_all_unique(&mut self.tag_ids, relation_ids);
+                push_all_unique_relations(&mut self.tag_details, relation_details);
+            }
+            "artists" | "artist" => {
+                push_all_unique(&mut self.artists, values);
+                push_all_unique(&mut self.people_ids, relation_ids);
+                push_all_unique_relations(&mut self.people_details, relation_details);
+            }
+            "groups" | "group" => {
+                push_all_unique(&mut self.groups, values);
+                push_all_unique(&mut self.people_ids, relation_ids);
+                push_all_unique_relations(&mut self.people_details, relation_details);
             }
+            "parodies" | "parody" => {
+                push_all_unique(&mut self.parodies, values);
+                push_all_unique_relations(&mut self.parody_details, relation_details);
+            }
+            "characters" | "character" => {
+                push_all_unique(&mut self.characters, values);
+                push_all_unique(&mut self.people_ids, relation_ids);
+                push_all_unique_relations(&mut self.people_details, relation_details);
+            }
+            "languages" | "language" => {
+                push_all_unique(&mut self.languages, values);
+                push_all_unique(&mut self.tag_ids, relation_ids);
+                push_all_unique_relations(&mut self.tag_details, relation_details);
+            }
+            "categories" | "category" => {
+                push_all_unique(&mut self.categories, values);
+                push_all_unique(&mut self.tag_ids, relation_ids);
+                push_all_unique_relations(&mut self.tag_details, relation_details);
+            }
+            "pages" => {
+                self.pages = values.first().and_then(|value| parse_u32_from_text(value));
+            }
+            _ => {}
         }
     }
+}
 
-    String::new()
+/// English (`h1.title`, or `og:title`), pretty (`span.pretty`) and Japanese (`h2.title`) titles.
+fn parse_gallery_titles(document: &Html) -> GalleryTitles {
+    let select_text = |selector_str: &str| {
+        let selector = Selector::parse(selector_str).expect("valid title selector");
+        document
+            .select(&selector)
+            .map(|node| normalize_text(&node.text().collect::<String>()))
+            .find(|value| !clean_title(value).is_empty())
+    };
+
+    let og_title = Selector::parse("meta[property=\"og:title\"]").expect("valid og:title selector");
+    let english = select_text("#info h1.title")
+        .or_else(|| select_text("h1.title"))
+        .or_else(|| {
+            document
+                .select(&og_title)
+                .filter_map(|node| node.value().attr("content"))
+                .map(|value| normalize_text(value.trim_end_matches(" - nhentai")))
+                .find(|value| !clean_title(value).is_empty())
+        });
+
+    GalleryTitles {
+        english,
+        pretty: select_text("#info h1.title span.pretty")
+            .or_else(|| select_text("h1.title span.pretty")),
+        japanese: select_text("#info h2.title").or_else(|| select_text("h2.title")),
+    }
 }
 
-fn parse_gallery_cover_url(document: &Html) -> String {
+fn parse_gallery_cover_url(document: &Html, base_url: &str) -> String {
     let img_selector = Selector::parse("#cover img, img#cover").expect("valid cover img selector");
     if let Some(img) = document.select(&img_selector).next() {
         if let Some(src) = img
@@ -311,7 +779,7 @@ fn parse_gallery_cover_url(document: &Html) -> String {
             .attr("data-src")
             .or_else(|| img.value().attr("src"))
         {
-            let normalized = normalize_url(src);
+            let normalized = normalize_url(src, base_url);
             if !normalized.is_empty() {
                 return normalized;
             }
@@ -324,15 +792,17 @@ fn parse_gallery_cover_url(document: &Html) -> String {
         .select(&meta_selector)
         .next()
         .and_then(|meta| meta.value().attr("content"))
-        .map(normalize_url)
+        .map(|content| normalize_url(content, base_url))
         .unwrap_or_default()
 }
 
-fn parse_tag_buckets(document: &Html) -> TagBuckets {
+fn parse_tag_buckets(document: &Html, base_url: &str) -> TagBuckets {
     let container_selector =
         Selector::parse("#tags .tag-container, .tag-container").expect("valid tag selector");
     let tag_selector = Selector::parse("a.tag").expect("valid tag selector");
     let tag_name_selector = Selector::parse("span.name").expect("valid tag name selector");
+    let tag_count_selector = Selector::parse("span.count").expect("valid tag count selector");
+    let time_selector = Selector::parse("time[datetime]").expect("valid time selector");
 
     let mut out = TagBuckets::default();
 
@@ -341,11 +811,16 @@ fn parse_tag_buckets(document: &Html) -> TagBuckets {
             continue;
         };
 
+        if label == "uploaded" {
+            out.upload_date = container
+                .select(&time_selector)
+                .find_map(|time| parse_datetime(time.value().attr("datetime")?));
+            continue;
+        }
+
         let mut values = Vec::new();
         let mut relation_ids = Vec::new();
         let mut relation_details: Vec<NhentaiRelation> = Vec::new();
-        let fallback_key = relation_key_for_label(&label);
-
         for anchor in container.select(&tag_selector) {
             let mut value = anchor
                 .select(&tag_name_selector)
@@ -362,33 +837,20 @@ fn parse_tag_buckets(document: &Html) -> TagBuckets {
             }
 
             let href = anchor.value().attr("href").unwrap_or_default();
-            let key = parse_relation_key_from_href(href)
-                .or_else(|| fallback_key.map(str::to_string))
-                .unwrap_or_default();
-            if key.is_empty() {
-                continue;
-            }
-
-            let slug = parse_relation_slug_from_href(href).or_else(|| slugify_identifier(&value));
-            let Some(slug) = slug else {
+            let Some(mut relation) = build_relation(&label, href, &value, base_url) else {
                 continue;
             };
+            relation.count = anchor
+                .select(&tag_count_selector)
+                .next()
+                .and_then(|el| parse_count(&el.text().collect::<String>()));
 
-            let relation_id = format!("nhentai-{key}:{slug}");
-            if !relation_ids.iter().any(|existing| existing == &relation_id) {
-                relation_ids.push(relation_id.clone());
+            if !relation_ids.iter().any(|existing| existing == &relation.id) {
+                relation_ids.push(relation.id.clone());
             }
 
-            let relation_name = if value.is_empty() {
-                slug.replace('-', " ")
-            } else {
-                value.clone()
-            };
-            if !relation_name.is_empty() && !relation_details.iter().any(|r| r.id == relation_id) {
-                relation_details.push(NhentaiRelation {
-                    id: relation_id,
-                    name: relation_name,
-                });
+            if !relation_details.iter().any(|r| r.id == relation.id) {
+                relation_details.push(relation);
             }
         }
 
@@ -396,62 +858,38 @@ fn parse_tag_buckets(document: &Html) -> TagBuckets {
             continue;
         }
 
-        if label == "tags" {
-            push_all_unique(&mut out.tags, values);
-            push_all_unique(&mut out.tag_ids, relation_ids);
-            push_all_unique_relations(&mut out.tag_details, relation_details);
-            continue;
-        }
-
-        if label == "artists" || label == "artist" {
-            push_all_unique(&mut out.artists, values);
-            push_all_unique(&mut out.people_ids, relation_ids);
-            push_all_unique_relations(&mut out.people_details, relation_details);
-            continue;
-        }
-
-        if label == "groups" || label == "group" {
-            push_all_unique(&mut out.groups, values);
-            push_all_unique(&mut out.people_ids, relation_ids);
-            push_all_unique_relations(&mut out.people_details, relation_details);
-            continue;
-        }
-
-        if label == "parodies" || label == "parody" {
-            push_all_unique(&mut out.parodies, values);
-            push_all_unique_relations(&mut out.parody_details, relation_details);
-            continue;
-        }
-
-        if label == "characters" || label == "character" {
-            push_all_unique(&mut out.characters, values);
-            push_all_unique(&mut out.people_ids, relation_ids);
-            push_all_unique_relations(&mut out.people_details, relation_details);
-            continue;
-        }
-
-        if label == "languages" || label == "language" {
-            push_all_unique(&mut out.languages, values);
-            push_all_unique(&mut out.tag_ids, relation_ids);
-            push_all_unique_relations(&mut out.tag_details, relation_details);
-            continue;
-        }
-
-        if label == "categories" || label == "category" {
-            push_all_unique(&mut out.categories, values);
-            push_all_unique(&mut out.tag_ids, relation_ids);
-            push_all_unique_relations(&mut out.tag_details, relation_details);
-            continue;
-        }
-
-        if label == "pages" {
-            out.pages = values.first().and_then(|value| parse_u32_from_text(value));
-        }
+        out.absorb(&label, values, relation_ids, relation_details);
     }
 
     out
 }
 
+/// Builds the `nhentai-{key}:{slug}` relation for a tag link, using the section label
+/// when the href does not carry the relation kind.
+pub fn build_relation(
+    label: &str,
+    href: &str,
+    name: &str,
+    base_url: &str,
+) -> Option<NhentaiRelation> {
+    let key = parse_relation_key_from_href(href, base_url)
+        .or_else(|| relation_key_for_label(label).map(str::to_string))?;
+    let slug =
+        parse_relation_slug_from_href(href, base_url).or_else(|| slugify_identifier(name))?;
+
+    let relation_name = if name.is_empty() {
+        slug.replace('-', " ")
+    } else {
+        name.to_string()
+    };
+
+    Some(NhentaiRelation {
+        id: format!("nhentai-{key}:{slug}"),
+        name: relation_name,
+        count: None,
+    })
+}
+
 fn relation_key_for_label(label: &str) -> Option<&'static str> {
     match label {
         "tags" | "tag" => Some("tags"),
@@ -465,14 +903,9 @@ fn relation_key_for_label(label: &str) -> Option<&'static str> {
     }
 }
 
-fn parse_relation_key_from_href(href: &str) -> Option<String> {
-    let normalized = normalize_url(href);
-    let path = normalized
-        .strip_prefix("https://nhentai.net/")
-        .or_else(|| normalized.strip_prefix("http://nhentai.net/"))
-        .or_else(|| normalized.strip_prefix("https://www.nhentai.net/"))
-        .or_else(|| normalized.strip_prefix("http://www.nhentai.net/"))
-        .unwrap_or(normalized.as_str());
+fn parse_relation_key_from_href(href: &str, base_url: &str) -> Option<String> {
+    let normalized = normalize_url(href, base_url);
+    let path = strip_site_prefix(&normalized, base_url);
     let cleaned = path
         .split_once('?')
         .map(|(left, _)| left)
@@ -494,14 +927,9 @@ fn parse_relation_key_from_href(href: &str) -> Option<String> {
     }
 }
 
-fn parse_relation_slug_from_href(href: &str) -> Option<String> {
-    let normalized = normalize_url(href);
-    let path = normalized
-        .strip_prefix("https://nhentai.net/")
-        .or_else(|| normalized.strip_prefix("http://nhentai.net/"))
-        .or_else(|| normalized.strip_prefix("https://www.nhentai.net/"))
-        .or_else(|| normalized.strip_prefix("http://www.nhentai.net/"))
-        .unwrap_or(normalized.as_str());
+fn parse_relation_slug_from_href(href: &str, base_url: &str) -> Option<String> {
+    let normalized = normalize_url(href, base_url);
+    let path = strip_site_prefix(&normalized, base_url);
     let cleaned = path
         .split_once('?')
         .map(|(left, _)| left)
@@ -513,12 +941,11 @@ fn parse_relation_slug_from_href(href: &str) -> Option<String> {
     cleaned
         .trim_matches('/')
         .split('/')
-        .filter(|segment| !segment.is_empty())
-        .next_back()
+        .rfind(|segment| !segment.is_empty())
         .map(|segment| segment.to_ascii_lowercase())
 }
 
-fn slugify_identifier(value: &str) -> Option<String> {
+pub fn slugify_identifier(value: &str) -> Option<String> {
     let mut slug = String::new();
     let mut prev_dash = false;
 
@@ -578,6 +1005,42 @@ fn parse_tag_container_label(container: &ElementRef<'_>) -> Option<String> {
     None
 }
 
+/// A numeric field (`upload_date`, `num_favorites`...) of the gallery JSON embedded in a
+/// page script.
+fn parse_script_number(document: &Html, key: &str) -> Option<u64> {
+    let script_selector = Selector::parse("script").expect("valid script selector");
+    let number_re = Regex::new(&format!(r#"\\?"{key}\\?"\s*:\s*(?P<value>\d+)"#))
+        .expect("valid script number regex");
+
+    document.select(&script_selector).find_map(|script| {
+        let body = script.text().collect::<String>();
+        number_re
+            .captures(&body)
+            .and_then(|caps| caps.name("value")?.as_str().parse().ok())
+    })
+}
+
+/// Favorites count of the favorite button: `.count`, or the `(1,234)` / `(1.2K)` in its
+/// label.
+fn parse_gallery_favorites(document: &Html) -> Option<u32> {
+    let count_selector = Selector::parse("#favorite .count").expect("valid favorite selector");
+    let button_selector = Selector::parse("#favorite").expect("valid favorite selector");
+
+    let count = match document.select(&count_selector).next() {
+        Some(count) => parse_count(&count.text().collect::<String>()),
+        None => {
+            let label = document
+                .select(&button_selector)
+                .next()?
+                .text()
+                .collect::<String>();
+            let (_, count) = label.split_once('(')?;
+            parse_count(count.split(')').next().unwrap_or_default())
+        }
+    };
+    count.and_then(|count| u32::try_from(count).ok())
+}
+
 fn parse_script_image_urls(document: &Html) -> Option<Vec<String>> {
     let script_selector = Selector::parse("script").expect("valid script selector");
     let media_re = Regex::new(r#"(?s)(?:\\?"media_id\\?"\s*:\s*\\?"(?P<id>\d+)\\?")"#)
@@ -620,13 +1083,7 @@ fn parse_script_image_urls(document: &Html) -> Option<Vec<String>> {
         let urls = page_types
             .iter()
             .enumerate()
-            .map(|(idx, page_type)| {
-                format!(
-                    "https://i.nhentai.net/galleries/{media_id}/{}.{}",
-                    idx + 1,
-                    image_extension(page_type)
-                )
-            })
+            .map(|(idx, page_type)| build_image_url(media_id, idx + 1, page_type))
             .collect::<Vec<_>>();
 
         return Some(urls);
@@ -635,7 +1092,7 @@ fn parse_script_image_urls(document: &Html) -> Option<Vec<String>> {
     None
 }
 
-fn parse_thumbnail_image_urls(document: &Html) -> Vec<String> {
+fn parse_thumbnail_image_urls(document: &Html, base_url: &str) -> Vec<String> {
     let thumb_selector = Selector::parse("#thumbnail-container img, .thumb-container img")
         .expect("valid thumb selector");
 
@@ -650,7 +1107,7 @@ fn parse_thumbnail_image_urls(document: &Html) -> Vec<String> {
             continue;
         };
 
-        let normalized = normalize_url(src);
+        let normalized = normalize_url(src, base_url);
         if normalized.is_empty() {
             continue;
         }
@@ -680,6 +1137,20 @@ fn thumbnail_to_image_url(thumbnail_url: &str) -> Option<String> {
     ))
 }
 
+pub fn build_image_url(media_id: &str, page: usize, page_type: &str) -> String {
+    format!(
+        "https://i.nhentai.net/galleries/{media_id}/{page}.{}",
+        image_extension(page_type)
+    )
+}
+
+pub fn build_cover_url(media_id: &str, page_type: &str) -> String {
+    format!(
+        "https://t.nhentai.net/galleries/{media_id}/cover.{}",
+        image_extension(page_type)
+    )
+}
+
 fn image_extension(token: &str) -> &str {
     match token {
         "p" => "png",
@@ -689,6 +1160,23 @@ fn image_extension(token: &str) -> &str {
     }
 }
 
+/// Reads nhentai's abbreviated counts: `431`, `1,234`, `12K`, `1.5M`.
+fn parse_count(value: &str) -> Option<u64> {
+    let value = value.trim().replace(',', "").to_ascii_lowercase();
+    let (number, multiplier) = match value.strip_suffix('k') {
+        Some(number) => (number, 1_000.0),
+        None => match value.strip_suffix('m') {
+            Some(number) => (number, 1_000_000.0),
+            None => (value.as_str(), 1.0),
+        },
+    };
+    let number: f64 = number.trim().parse().ok()?;
+    if !number.is_finite() || number < 0.0 {
+        return None;
+    }
+    Some((number * multiplier).round() as u64)
+}
+
 fn parse_u32_from_text(value: &str) -> Option<u32> {
     let digits = value
         .chars()
@@ -730,15 +1218,15 @@ fn deduplicate_strings(values: Vec<String>) -> Vec<String> {
     out
 }
 
-fn is_valid_gallery_id(id: &str) -> bool {
+pub fn is_valid_gallery_id(id: &str) -> bool {
     !id.is_empty() && id.chars().all(|ch| ch.is_ascii_digit())
 }
 
-fn normalize_text(value: &str) -> String {
+pub fn normalize_text(value: &str) -> String {
     value.split_whitespace().collect::<Vec<_>>().join(" ")
 }
 
-fn clean_title(value: &str) -> String {
+pub fn clean_title(value: &str) -> String {
     let mut result = String::with_capacity(value.len());
     let mut paren_depth = 0u32;
     let mut bracket_depth = 0u32;
@@ -757,7 +1245,46 @@ fn clean_title(value: &str) -> String {
     normalize_text(&result)
 }
 
-fn normalize_url(value: &str) -> String {
+/// Trims a configured site origin (e.g. `https://mirror.example/`) down to
+/// `scheme://host[/prefix]`, falling back to nhentai.net when it is empty.
+pub fn normalize_base_url(value: &str) -> String {
+    let trimmed = value.trim().trim_end_matches('/');
+    if trimmed.is_empty() {
+        return DEFAULT_BASE_URL.to_string();
+    }
+
+    if trimmed.starts_with("https://") || trimmed.starts_with("http://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{trimmed}")
+    }
+}
+
+/// Returns the site-relative path of `url` when it points at the configured origin or
+/// at nhentai.net itself; other values are returned unchanged.
+fn strip_site_prefix<'a>(url: &'a str, base_url: &str) -> &'a str {
+    let without_scheme = strip_scheme(url);
+    let base_host = strip_scheme(base_url);
+
+    [base_host, "nhentai.net", "www.nhentai.net"]
+        .iter()
+        .find_map(|host| {
+            without_scheme
+                .strip_prefix(host)
+                .filter(|rest| rest.is_empty() || rest.starts_with('/'))
+        })
+        .map(|rest| rest.trim_start_matches('/'))
+        .unwrap_or(without_scheme)
+}
+
+fn strip_scheme(value: &str) -> &str {
+    value
+        .strip_prefix("https://")
+        .or_else(|| value.strip_prefix("http://"))
+        .unwrap_or(value)
+}
+
+fn normalize_url(value: &str, base_url: &str) -> String {
     let trimmed = value.trim();
     if trimmed.is_empty() {
         return String::new();
@@ -772,13 +1299,17 @@ fn normalize_url(value: &str) -> String {
     }
 
     if trimmed.starts_with('/') {
-        return format!("https://nhentai.net{trimmed}");
+        return format!("{base_url}{trimmed}");
     }
 
-    format!("https://nhentai.net/{trimmed}")
+    format!("{base_url}/{trimmed}")
+}
+
+fn encode_path_segment(value: &str) -> String {
+    encode_query_component(value).replace('+', "%20")
 }
 
-fn encode_query_component(value: &str) -> String {
+pub fn encode_query_component(value: &str) -> String {
     let mut encoded = String::with_capacity(value.len());
 
     for b in value.as_bytes() {
@@ -798,15 +1329,37 @@ fn encode_query_component(value: &str) -> String {
 mod tests {
     use super::*;
 
+    fn english() -> Vec<String> {
+        vec!["english".to_string()]
+    }
+
     #[test]
     fn build_search_url_adds_english_prefix() {
-        let url = build_search_url("soft", None, None).expect("url");
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            None,
+            SearchSort::Recent,
+            &english(),
+            None,
+            &Blacklist::default(),
+        )
+        .expect("url");
         assert_eq!(url, "https://nhentai.net/search/?q=language%3Aenglish+soft");
     }
 
     #[test]
     fn build_search_url_appends_page() {
-        let url = build_search_url("soft", Some(3), None).expect("url");
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            Some(3),
+            SearchSort::Recent,
+            &english(),
+            None,
+            &Blacklist::default(),
+        )
+        .expect("url");
         assert_eq!(
             url,
             "https://nhentai.net/search/?q=language%3Aenglish+soft&page=3"
@@ -815,13 +1368,31 @@ mod tests {
 
     #[test]
     fn build_search_url_page_one_omits_param() {
-        let url = build_search_url("soft", Some(1), None).expect("url");
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            Some(1),
+            SearchSort::Recent,
+            &english(),
+            None,
+            &Blacklist::default(),
+        )
+        .expect("url");
         assert_eq!(url, "https://nhentai.net/search/?q=language%3Aenglish+soft");
     }
 
     #[test]
     fn build_search_url_appends_custom_params() {
-        let url = build_search_url("soft", None, Some("-yaoi")).expect("url");
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            None,
+            SearchSort::Recent,
+            &english(),
+            Some("-yaoi"),
+            &Blacklist::default(),
+        )
+        .expect("url");
         assert_eq!(
             url,
             "https://nhentai.net/search/?q=language%3Aenglish+soft+-yaoi"
@@ -830,21 +1401,255 @@ mod tests {
 
     #[test]
     fn build_search_url_ignores_empty_custom_params() {
-        let url = build_search_url("soft", None, Some("  ")).expect("url");
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            None,
+            SearchSort::Recent,
+            &english(),
+            Some("  "),
+            &Blacklist::default(),
+        )
+        .expect("url");
         assert_eq!(url, "https://nhentai.net/search/?q=language%3Aenglish+soft");
     }
 
+    #[test]
+    fn build_search_url_without_languages_has_no_prefix() {
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            None,
+            SearchSort::Recent,
+            &[],
+            None,
+            &Blacklist::default(),
+        )
+        .expect("url");
+        assert_eq!(url, "https://nhentai.net/search/?q=soft");
+    }
+
+    #[test]
+    fn build_search_url_joins_several_languages() {
+        let languages = vec!["japanese".to_string(), "chinese".to_string()];
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            None,
+            SearchSort::Recent,
+            &languages,
+            None,
+            &Blacklist::default(),
+        )
+        .expect("url");
+        assert_eq!(
+            url,
+            "https://nhentai.net/search/?q=language%3Ajapanese+OR+language%3Achinese+soft"
+        );
+    }
+
+    #[test]
+    fn build_search_url_keeps_sort_on_later_pages() {
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            Some(2),
+            SearchSort::PopularWeek,
+            &english(),
+            None,
+            &Blacklist::default(),
+        )
+        .expect("url");
+        assert_eq!(
+            url,
+            "https://nhentai.net/search/?q=language%3Aenglish+soft&sort=popular-week&page=2"
+        );
+    }
+
+    #[test]
+    fn build_search_url_negates_blacklisted_tags() {
+        let url = build_search_url(
+            DEFAULT_BASE_URL,
+            "soft",
+            None,
+            SearchSort::Recent,
+            &[],
+            Some("-yaoi"),
+            &Blacklist::parse("netorare, artist:some artist"),
+        )
+        .expect("url");
+        assert_eq!(
+            url,
+            "https://nhentai.net/search/?q=soft+-yaoi+-tag%3Anetorare+-artist%3A%22some+artist%22"
+        );
+    }
+
+    #[test]
+    fn split_sort_term_extracts_override() {
+        assert_eq!(
+            split_sort_term("soft sort:Popular-Today full color"),
+            (
+                "soft full color".to_string(),
+                Some(SearchSort::PopularToday)
+            )
+        );
+        assert_eq!(
+            split_sort_term("soft sort:sideways"),
+            ("soft sort:sideways".to_string(), None)
+        );
+        assert_eq!(SearchSort::parse("all-time"), Some(SearchSort::Popular));
+        assert_eq!(SearchSort::Recent.as_param(), None);
+    }
+
+    #[test]
+    fn parse_favorites_filter_reads_optional_query() {
+        assert_eq!(
+            parse_favorites_filter("nhentai-favorites"),
+            Some(String::new())
+        );
+        assert_eq!(
+            parse_favorites_filter(" NHentai-Favorites: full color "),
+            Some("full color".to_string())
+        );
+        assert_eq!(parse_favorites_filter("nhentai-favoritesx"), None);
+        assert_eq!(parse_favorites_filter("nhentai-artist:foo"), None);
+    }
+
+    #[test]
+    fn build_favorites_url_adds_query_and_page() {
+        assert_eq!(
+            build_favorites_url(DEFAULT_BASE_URL, "", Some(1)),
+            "https://nhentai.net/favorites/"
+        );
+        assert_eq!(
+            build_favorites_url(DEFAULT_BASE_URL, "full color", Some(2)),
+            "https://nhentai.net/favorites/?q=full+color&page=2"
+        );
+    }
+
+    #[test]
+    fn parse_search_pagination_reads_last_page() {
+        let html = r#"
+        <div id="content">
+          <section class="pagination">
+            <a href="/search/?q=soft&amp;page=2" class="previous"></a>
+            <a href="/search/?q=soft&amp;page=2" class="page">2</a>
+            <a href="/search/?q=soft&amp;page=3" class="page current">3</a>
+            <a href="/search/?q=soft&amp;page=4" class="page">4</a>
+            <a href="/search/?q=soft&amp;page=4" class="next"></a>
+            <a href="/search/?q=soft&amp;page=41" class="last"></a>
+          </section>
+        </div>
+        "#;
+
+        let pagination = parse_search_pagination(html, 3);
+        assert_eq!(pagination, SearchPagination { next_page: Some(4) });
+        assert_eq!(pagination.next_page_key(), Some("4".to_string()));
+    }
+
+    #[test]
+    fn parse_search_pagination_on_last_page() {
+        let html = r#"
+        <section class="pagination">
+          <a href="?page=1" class="first"></a>
+          <a href="?page=1" class="page">1</a>
+          <a href="?page=2" class="page current">2</a>
+        </section>
+        "#;
+
+        assert_eq!(
+            parse_search_pagination(html, 2),
+            SearchPagination { next_page: None }
+        );
+    }
+
+    #[test]
+    fn parse_search_html_reads_favorites_cards() {
+        let html = r#"
+        <div class="container" id="favcontainer">
+          <div class="gallery-favorite" data-id="12345">
+            <div class="gallery" data-tags="12227">
+              <a class="cover" href="/g/12345/">
+                <img data-src="//t3.nhentai.net/galleries/111/thumb.jpg" />
+                <div class="caption">Favorite One</div>
+              </a>
+            </div>
+          </div>
+        </div>
+        <section class="pagination"><a class="next" href="?page=2"></a></section>
+        "#;
+
+        assert!(!is_login_page(html));
+        let items = parse_search_html(html, DEFAULT_BASE_URL);
+        assert_eq!(items.len(), 1);
+        assert_eq!(items[0].id.as_deref(), Some("12345"));
+        assert_eq!(items[0].title, "Favorite One");
+        assert_eq!(parse_search_pagination(html, 1).next_page, Some(2));
+    }
+
+    #[test]
+    fn is_login_page_detects_login_form() {
+        let html = r#"<form method="post" action="/login/?next=/favorites/">
+            <input name="username_or_email" type="text"></form>"#;
+        assert!(is_login_page(html));
+    }
+
     #[test]
     fn parse_lookup_gallery_id_supports_prefix_and_url() {
         assert_eq!(
-            parse_lookup_gallery_id("nhentai:12345"),
+            parse_lookup_gallery_id("nhentai:12345", DEFAULT_BASE_URL),
             Some("12345".to_string())
         );
         assert_eq!(
-            parse_lookup_gallery_id("https://nhentai.net/g/67890/"),
+            parse_lookup_gallery_id("https://nhentai.net/g/67890/", DEFAULT_BASE_URL),
             Some("67890".to_string())
         );
-        assert_eq!(parse_lookup_gallery_id("soft sample"), None);
+        assert_eq!(
+            parse_lookup_gallery_id("soft sample", DEFAULT_BASE_URL),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_lookup_gallery_id_recognizes_configured_mirror() {
+        let base = "http://localhost:8080";
+        assert_eq!(
+            parse_lookup_gallery_id("http://localhost:8080/g/4242/", base),
+            Some("4242".to_string())
+        );
+        assert_eq!(
+            parse_lookup_gallery_id("https://nhentai.net/g/4242/", base),
+            Some("4242".to_string())
+        );
+        assert_eq!(
+            parse_lookup_gallery_id("https://other.example/g/4242/", base),
+            None
+        );
+    }
+
+    #[test]
+    fn parse_gallery_html_uses_configured_base_url() {
+        let html = r#"
+        <html>
+          <body>
+            <h1 class="title">Mirror Gallery</h1>
+            <div id="tags">
+              <div class="tag-container field-name">
+                Artists:
+                <span class="tags"><a class="tag" href="https://mirror.example/artist/bai-asuka/"><span class="name">bai asuka</span></a></span>
+              </div>
+            </div>
+          </body>
+        </html>
+        "#;
+
+        let result =
+            parse_gallery_html(html, "42", "https://mirror.example").expect("gallery should parse");
+        assert_eq!(result.gallery_url, "https://mirror.example/g/42/");
+        assert_eq!(
+            result.people_ids,
+            vec!["nhentai-artist:bai-asuka".to_string()]
+        );
     }
 
     #[test]
@@ -864,7 +1669,7 @@ mod tests {
         </div>
         "#;
 
-        let results = parse_search_html(html);
+        let results = parse_search_html(html, DEFAULT_BASE_URL);
         assert_eq!(results.len(), 2);
 
         assert_eq!(results[0].id, Some("12345".to_string()));
@@ -884,6 +1689,111 @@ mod tests {
         assert_eq!(results[1].gallery_url, "https://nhentai.net/g/67890/");
     }
 
+    #[test]
+    fn parse_search_html_resolves_known_data_tags() {
+        let html = r#"
+        <div class="gallery" data-tags="6346 33172 99999">
+          <a class="cover" href="/g/12345/">
+            <img data-src="//t3.nhentai.net/galleries/111/thumb.jpg" />
+          </a>
+          <div class="caption">Soft Sample</div>
+        </div>
+        "#;
+
+        let results = parse_search_html(html, DEFAULT_BASE_URL);
+        assert_eq!(results[0].site_tag_ids, vec![6346, 33172, 99999]);
+        assert_eq!(results[0].languages, vec!["japanese".to_string()]);
+        assert_eq!(results[0].categories, vec!["doujinshi".to_string()]);
+        assert_eq!(
+            results[0].tag_ids,
+            vec![
+                "nhentai-language:japanese".to_string(),
+                "nhentai-category:doujinshi".to_string(),
+            ]
+        );
+        assert!(results[0].tags.is_empty());
+    }
+
+    #[test]
+    fn parse_browse_mode_reads_reserved_names() {
+        assert_eq!(
+            parse_browse_mode("nhentai:popular"),
+            Some(BrowseMode::Popular)
+        );
+        assert_eq!(parse_browse_mode(" NHentai:New "), Some(BrowseMode::Recent));
+        assert_eq!(parse_browse_mode("nhentai:12345"), None);
+        assert_eq!(parse_browse_mode("popular"), None);
+    }
+
+    #[test]
+    fn build_browse_url_pages_only_recent() {
+        assert_eq!(
+            build_browse_url(DEFAULT_BASE_URL, BrowseMode::Recent, Some(3)),
+            "https://nhentai.net/?page=3"
+        );
+        assert_eq!(
+            build_browse_url(DEFAULT_BASE_URL, BrowseMode::Recent, Some(1)),
+            "https://nhentai.net/"
+        );
+        assert_eq!(
+            build_browse_url(DEFAULT_BASE_URL, BrowseMode::Popular, Some(3)),
+            "https://nhentai.net/"
+        );
+    }
+
+    #[test]
+    fn parse_browse_html_splits_homepage_blocks() {
+        let html = r#"
+        <div class="container index-container index-popular">
+          <h2>Popular Now</h2>
+          <div class="gallery" data-tags="12227">
+            <a class="cover" href="/g/111/"><img data-src="//t3.nhentai.net/galleries/1/thumb.jpg" />
+            <div class="caption">Popular One</div></a>
+          </div>
+        </div>
+        <div class="container index-container">
+          <h2>New Uploads</h2>
+          <div class="gallery" data-tags="6346">
+            <a class="cover" href="/g/222/"><img data-src="//t3.nhentai.net/galleries/2/thumb.jpg" />
+            <div class="caption">New One</div></a>
+          </div>
+          <div class="gallery" data-tags="29963">
+            <a class="cover" href="/g/333/"><img data-src="//t3.nhentai.net/galleries/3/thumb.jpg" />
+            <div class="caption">New Two</div></a>
+          </div>
+        </div>
+        "#;
+
+        let ids = |mode| {
+            parse_browse_html(html, mode, DEFAULT_BASE_URL)
+                .into_iter()
+                .filter_map(|g| g.id)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(ids(BrowseMode::Popular), vec!["111"]);
+        assert_eq!(ids(BrowseMode::Recent), vec!["222", "333"]);
+    }
+
+    #[test]
+    fn parse_page_gallery_id_reads_canonical_or_heading() {
+        let canonical = r#"<head><link rel="canonical" href="https://nhentai.net/g/4242/"></head>"#;
+        assert_eq!(
+            parse_page_gallery_id(canonical, DEFAULT_BASE_URL),
+            Some("4242".to_string())
+        );
+        let heading = r#"<h3 id="gallery_id"><span class="hash">#</span>12345</h3>"#;
+        assert_eq!(
+            parse_page_gallery_id(heading, DEFAULT_BASE_URL),
+            Some("12345".to_string())
+        );
+        assert_eq!(
+            parse_page_gallery_id("<h1>Home</h1>", DEFAULT_BASE_URL),
+            None
+        );
+        assert!(is_random_lookup(" NHentai:Random "));
+        assert!(!is_random_lookup("nhentai:randomly"));
+    }
+
     #[test]
     fn parse_search_html_skips_invalid_rows() {
         let html = r#"
@@ -898,7 +1808,7 @@ mod tests {
         </div>
         "#;
 
-        let results = parse_search_html(html);
+        let results = parse_search_html(html, DEFAULT_BASE_URL);
         assert!(results.is_empty());
     }
 
@@ -924,7 +1834,7 @@ mod tests {
               </div>
               <div class="tag-container field-name">
                 <span class="name">Tags:</span>
-                <span class="tags"><a class="tag" href="/tag/full-color/"><span class="name">full color</span></a></span>
+                <span class="tags"><a class="tag" href="/tag/full-color/"><span class="name">full color</span><span class="count">12K</span></a></span>
               </div>
               <div class="tag-container field-name">
                 <span class="name">Languages:</span>
@@ -938,6 +1848,13 @@ mod tests {
                 <span class="name">Pages:</span>
                 <span class="tags"><a class="tag"><span class="name">24</span></a></span>
               </div>
+              <div class="buttons">
+                <a class="btn btn-primary" id="favorite"><span class="text">Favorite <span class="count">1.2K</span></span></a>
+              </div>
+              <div class="tag-container field-name">
+                Uploaded:
+                <span class="tags"><time class="nobold" datetime="2021-05-01T12:34:56.789012+00:00">5 years ago</time></span>
+              </div>
             </div>
             <script>
               window._gallery = {"media_id":"555","images":{"pages":[{"t":"j"},{"t":"p"}]}};
@@ -946,7 +1863,8 @@ mod tests {
         </html>
         "#;
 
-        let result = parse_gallery_html(html, "12345").expect("gallery should parse");
+        let result =
+            parse_gallery_html(html, "12345", DEFAULT_BASE_URL).expect("gallery should parse");
         assert_eq!(result.id, Some("12345".to_string()));
         assert_eq!(result.title, "Sample Gallery");
         assert_eq!(result.gallery_url, "https://nhentai.net/g/12345/");
@@ -971,11 +1889,13 @@ mod tests {
             vec![
                 NhentaiRelation {
                     id: "nhentai-artist:artist-one".to_string(),
-                    name: "artist-one".to_string()
+                    name: "artist-one".to_string(),
+                    count: None,
                 },
                 NhentaiRelation {
                     id: "nhentai-group:group-one".to_string(),
-                    name: "group-one".to_string()
+                    name: "group-one".to_string(),
+                    count: None,
                 }
             ]
         );
@@ -992,19 +1912,24 @@ mod tests {
             vec![
                 NhentaiRelation {
                     id: "nhentai-tags:full-color".to_string(),
-                    name: "full color".to_string()
+                    name: "full color".to_string(),
+                    count: Some(12_000),
                 },
                 NhentaiRelation {
                     id: "nhentai-language:english".to_string(),
-                    name: "english".to_string()
+                    name: "english".to_string(),
+                    count: None,
                 },
                 NhentaiRelation {
                     id: "nhentai-category:doujinshi".to_string(),
-                    name: "doujinshi".to_string()
+                    name: "doujinshi".to_string(),
+                    count: None,
                 }
             ]
         );
         assert_eq!(result.pages, Some(24));
+        assert_eq!(result.upload_date, Some(1_619_872_496));
+        assert_eq!(result.favorites, Some(1200));
         assert_eq!(
             result.images,
             vec![
@@ -1014,6 +1939,62 @@ mod tests {
         );
     }
 
+    #[test]
+    fn parse_gallery_html_keeps_bracketed_title_info() {
+        let html = r#"
+        <div id="info">
+          <h1 class="title"><span class="before">(C99) [Circle (Artist)] </span><span class="pretty">Soft Love</span><span class="after"> [English] [Some Scans] [Decensored]</span></h1>
+        </div>
+        "#;
+
+        let result =
+            parse_gallery_html(html, "12345", DEFAULT_BASE_URL).expect("gallery should parse");
+        assert_eq!(result.title, "Soft Love");
+        assert_eq!(result.title_info.event.as_deref(), Some("C99"));
+        assert_eq!(result.title_info.circle.as_deref(), Some("Circle"));
+        assert_eq!(result.title_info.translator.as_deref(), Some("Some Scans"));
+        assert!(result.title_info.decensored);
+        assert!(!result.title_info.digital);
+    }
+
+    #[test]
+    fn parse_gallery_html_collects_alternate_titles() {
+        let html = r#"
+        <div id="info">
+          <h1 class="title"><span class="before">[Circle] </span><span class="pretty">Soft Love</span><span class="after"> [English]</span></h1>
+          <h2 class="title"><span class="before">[サークル] </span><span class="pretty">やわらかい恋</span><span class="after"></span></h2>
+        </div>
+        "#;
+
+        let result =
+            parse_gallery_html(html, "12345", DEFAULT_BASE_URL).expect("gallery should parse");
+        assert_eq!(result.title, "Soft Love");
+        assert_eq!(result.alternate_titles, vec!["やわらかい恋".to_string()]);
+        assert_eq!(result.japanese_title, Some("やわらかい恋".to_string()));
+    }
+
+    #[test]
+    fn parse_gallery_html_reads_favorites_from_button_label() {
+        let html = r#"
+        <h1 class="title">Soft Love</h1>
+        <a class="btn btn-primary" id="favorite"><i class="fa fa-heart"></i> <span class="text">Favorite <span class="nobold">(1.2K)</span></span></a>
+        "#;
+
+        let result =
+            parse_gallery_html(html, "12345", DEFAULT_BASE_URL).expect("gallery should parse");
+        assert_eq!(result.favorites, Some(1200));
+    }
+
+    #[test]
+    fn parse_count_reads_abbreviated_counts() {
+        assert_eq!(parse_count("431"), Some(431));
+        assert_eq!(parse_count("1,234"), Some(1_234));
+        assert_eq!(parse_count("12K"), Some(12_000));
+        assert_eq!(parse_count(" 1.5M "), Some(1_500_000));
+        assert_eq!(parse_count(""), None);
+        assert_eq!(parse_count("many"), None);
+    }
+
     #[test]
     fn parse_gallery_html_uses_thumbnail_fallback() {
         let html = r#"
@@ -1027,7 +2008,8 @@ mod tests {
         </html>
         "#;
 
-        let result = parse_gallery_html(html, "987").expect("gallery should parse");
+        let result =
+            parse_gallery_html(html, "987", DEFAULT_BASE_URL).expect("gallery should parse");
         assert_eq!(
             result.images,
             vec!["https://i.nhentai.net/galleries/987/1.jpg".to_string()]
@@ -1048,7 +2030,8 @@ mod tests {
         </html>
         "#;
 
-        let result = parse_gallery_html(html, "123").expect("gallery should parse");
+        let result =
+            parse_gallery_html(html, "123", DEFAULT_BASE_URL).expect("gallery should parse");
         assert_eq!(
             result.images,
             vec![
@@ -1065,13 +2048,14 @@ mod tests {
           <body>
             <h1 class="title">Escaped Script</h1>
             <script>
-              window._n_app = JSON.parse("{\"media_id\":\"700\",\"images\":{\"pages\":[{\"t\":\"j\"},{\"t\":\"w\"}]}}");
+              window._n_app = JSON.parse("{\"media_id\":\"700\",\"images\":{\"pages\":[{\"t\":\"j\"},{\"t\":\"w\"}]},\"upload_date\":1619872496,\"num_favorites\":77}");
             </script>
           </body>
         </html>
         "#;
 
-        let result = parse_gallery_html(html, "700").expect("gallery should parse");
+        let result =
+            parse_gallery_html(html, "700", DEFAULT_BASE_URL).expect("gallery should parse");
         assert_eq!(
             result.images,
             vec![
@@ -1079,6 +2063,8 @@ mod tests {
                 "https://i.nhentai.net/galleries/700/2.webp".to_string()
             ]
         );
+        assert_eq!(result.upload_date, Some(1_619_872_496));
+        assert_eq!(result.favorites, Some(77));
     }
 
     #[test]
@@ -1102,7 +2088,8 @@ mod tests {
         </html>
         "#;
 
-        let result = parse_gallery_html(html, "629637").expect("gallery should parse");
+        let result =
+            parse_gallery_html(html, "629637", DEFAULT_BASE_URL).expect("gallery should parse");
         assert_eq!(result.artists, vec!["bai asuka".to_string()]);
         assert_eq!(
             result.people_ids,
@@ -1112,15 +2099,45 @@ mod tests {
             result.people_details,
             vec![NhentaiRelation {
                 id: "nhentai-artist:bai-asuka".to_string(),
-                name: "bai asuka".to_string()
+                name: "bai asuka".to_string(),
+                count: Some(574),
             }]
         );
     }
 
+    fn relation_search_term(value: &str) -> Option<String> {
+        parse_relation_key(value).map(|key| key.search_term())
+    }
+
+    #[test]
+    fn parse_relation_key_splits_namespace_and_slug() {
+        assert_eq!(
+            parse_relation_key("nhentai-tags:full-color"),
+            Some(RelationKey {
+                namespace: "tag",
+                slug: "full-color".to_string()
+            })
+        );
+        assert_eq!(parse_relation_key("nhentai-unknown:x"), None);
+    }
+
+    #[test]
+    fn build_relation_listing_url_pages_and_sorts() {
+        let key = parse_relation_key("nhentai-artist:bai-asuka").unwrap();
+        assert_eq!(
+            build_relation_listing_url(DEFAULT_BASE_URL, &key, None, SearchSort::Recent),
+            "https://nhentai.net/artist/bai-asuka/"
+        );
+        assert_eq!(
+            build_relation_listing_url(DEFAULT_BASE_URL, &key, Some(3), SearchSort::PopularWeek),
+            "https://nhentai.net/artist/bai-asuka/popular-week?page=3"
+        );
+    }
+
     #[test]
     fn parse_relation_search_term_group() {
         assert_eq!(
-            parse_relation_search_term("nhentai-group:maiju"),
+            relation_search_term("nhentai-group:maiju"),
             Some("group:maiju".to_string())
         );
     }
@@ -1128,23 +2145,23 @@ mod tests {
     #[test]
     fn parse_relation_search_term_artist() {
         assert_eq!(
-            parse_relation_search_term("nhentai-artist:sasaki-musashi"),
-            Some("artist:sasaki-musashi".to_string())
+            relation_search_term("nhentai-artist:sasaki-musashi"),
+            Some(r#"artist:"sasaki-musashi""#.to_string())
         );
     }
 
     #[test]
     fn parse_relation_search_term_tags_maps_to_tag() {
         assert_eq!(
-            parse_relation_search_term("nhentai-tags:full-color"),
-            Some("tag:full-color".to_string())
+            relation_search_term("nhentai-tags:full-color"),
+            Some(r#"tag:"full-color""#.to_string())
         );
     }
 
     #[test]
     fn parse_relation_search_term_parody() {
         assert_eq!(
-            parse_relation_search_term("nhentai-parody:naruto"),
+            relation_search_term("nhentai-parody:naruto"),
             Some("parody:naruto".to_string())
         );
     }
@@ -1152,7 +2169,7 @@ mod tests {
     #[test]
     fn parse_relation_search_term_character() {
         assert_eq!(
-            parse_relation_search_term("nhentai-character:hinata"),
+            relation_search_term("nhentai-character:hinata"),
             Some("character:hinata".to_string())
         );
     }
@@ -1160,7 +2177,7 @@ mod tests {
     #[test]
     fn parse_relation_search_term_language() {
         assert_eq!(
-            parse_relation_search_term("nhentai-language:english"),
+            relation_search_term("nhentai-language:english"),
             Some("language:english".to_string())
         );
     }
@@ -1168,27 +2185,39 @@ mod tests {
     #[test]
     fn parse_relation_search_term_category() {
         assert_eq!(
-            parse_relation_search_term("nhentai-category:doujinshi"),
+            relation_search_term("nhentai-category:doujinshi"),
             Some("category:doujinshi".to_string())
         );
     }
 
+    #[test]
+    fn parse_relation_search_term_keeps_hyphenated_slugs() {
+        assert_eq!(
+            relation_search_term("nhentai-category:non-h"),
+            Some(r#"category:"non-h""#.to_string())
+        );
+        assert_eq!(
+            relation_search_term("nhentai-tags:x-eros"),
+            Some(r#"tag:"x-eros""#.to_string())
+        );
+    }
+
     #[test]
     fn parse_relation_search_term_invalid_category() {
-        assert_eq!(parse_relation_search_term("nhentai-unknown:value"), None);
+        assert_eq!(relation_search_term("nhentai-unknown:value"), None);
     }
 
     #[test]
     fn parse_relation_search_term_not_a_relation_id() {
-        assert_eq!(parse_relation_search_term("nhentai:12345"), None);
-        assert_eq!(parse_relation_search_term("some random text"), None);
-        assert_eq!(parse_relation_search_term(""), None);
+        assert_eq!(relation_search_term("nhentai:12345"), None);
+        assert_eq!(relation_search_term("some random text"), None);
+        assert_eq!(relation_search_term(""), None);
     }
 
     #[test]
     fn parse_relation_search_term_empty_slug() {
-        assert_eq!(parse_relation_search_term("nhentai-artist:"), None);
-        assert_eq!(parse_relation_search_term("nhentai-artist:  "), None);
+        assert_eq!(relation_search_term("nhentai-artist:"), None);
+        assert_eq!(relation_search_term("nhentai-artist:  "), None);
     }
 
     #[test]
@@ -1211,13 +2240,15 @@ mod tests {
         </html>
         "#;
 
-        let result = parse_gallery_html(html, "282849").expect("gallery should parse");
+        let result =
+            parse_gallery_html(html, "282849", DEFAULT_BASE_URL).expect("gallery should parse");
         assert_eq!(result.parodies, vec!["naruto".to_string()]);
         assert_eq!(
             result.parody_details,
             vec![NhentaiRelation {
                 id: "nhentai-parody:naruto".to_string(),
-                name: "naruto".to_string()
+                name: "naruto".to_string(),
+                count: None,
             }]
         );
         assert!(result.tag_details.is_empty());
@@ -0,0 +1,358 @@
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Less,
+    LessOrEqual,
+    Equal,
+    GreaterOrEqual,
+    Greater,
+}
+
+impl Comparison {
+    fn as_str(self) -> &'static str {
+        match self {
+            Comparison::Less => "<",
+            Comparison::LessOrEqual => "<=",
+            Comparison::Equal => "",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Greater => ">",
+        }
+    }
+
+    fn split(value: &str) -> (Comparison, &str) {
+        for (prefix, comparison) in [
+            ("<=", Comparison::LessOrEqual),
+            (">=", Comparison::GreaterOrEqual),
+            ("<", Comparison::Less),
+            (">", Comparison::Greater),
+            ("=", Comparison::Equal),
+        ] {
+            if let Some(rest) = value.strip_prefix(prefix) {
+                return (comparison, rest);
+            }
+        }
+        (Comparison::Equal, value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AgeUnit {
+    Hours,
+    Days,
+    Weeks,
+    Months,
+    Years,
+}
+
+impl AgeUnit {
+    fn suffix(self) -> char {
+        match self {
+            AgeUnit::Hours => 'h',
+            AgeUnit::Days => 'd',
+            AgeUnit::Weeks => 'w',
+            AgeUnit::Months => 'm',
+            AgeUnit::Years => 'y',
+        }
+    }
+
+    fn from_suffix(suffix: char) -> Option<AgeUnit> {
+        match suffix.to_ascii_lowercase() {
+            'h' => Some(AgeUnit::Hours),
+            'd' => Some(AgeUnit::Days),
+            'w' => Some(AgeUnit::Weeks),
+            'm' => Some(AgeUnit::Months),
+            'y' => Some(AgeUnit::Years),
+            _ => None,
+        }
+    }
+}
+
+/// Namespaces nhentai's search understands; any other `word:value` is title text.
+const NAMESPACES: [&str; 9] = [
+    "tag",
+    "artist",
+    "group",
+    "parody",
+    "character",
+    "language",
+    "category",
+    "pages",
+    "uploaded",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryTerm {
+    /// Free text, matched against titles.
+    Text(String),
+    /// `tag:x`, `artist:"two words"`, or `-tag:x` when excluded.
+    Namespaced {
+        namespace: String,
+        value: String,
+        exclude: bool,
+    },
+    /// `pages:>N`
+    Pages(Comparison, u32),
+    /// `uploaded:<7d`
+    Uploaded(Comparison, u32, AgeUnit),
+    /// Alternatives joined with `OR`.
+    AnyOf(Vec<QueryTerm>),
+}
+
+impl QueryTerm {
+    fn render(&self) -> String {
+        match self {
+            QueryTerm::Text(text) => text.clone(),
+            QueryTerm::Namespaced {
+                namespace,
+                value,
+                exclude,
+            } => format!(
+                "{}{namespace}:{}",
+                if *exclude { "-" } else { "" },
+                quote(value)
+            ),
+            QueryTerm::Pages(cmp, pages) => format!("pages:{}{pages}", cmp.as_str()),
+            QueryTerm::Uploaded(cmp, amount, unit) => {
+                format!("uploaded:{}{amount}{}", cmp.as_str(), unit.suffix())
+            }
+            QueryTerm::AnyOf(terms) => terms
+                .iter()
+                .map(QueryTerm::render)
+                .collect::<Vec<_>>()
+                .join(" OR "),
+        }
+    }
+}
+
+/// A search in nhentai's query syntax, built from typed terms and rendered with the
+/// quoting the site expects.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SearchQuery {
+    pub terms: Vec<QueryTerm>,
+}
+
+impl SearchQuery {
+    pub fn new() -> SearchQuery {
+        SearchQuery::default()
+    }
+
+    /// Parses a user-typed query. Quoted values stay together and namespaced, `pages:`
+    /// and `uploaded:` terms become typed terms; anything else is kept as text.
+    pub fn parse(query: &str) -> SearchQuery {
+        SearchQuery {
+            terms: tokenize(query)
+                .iter()
+                .map(|token| parse_term(token))
+                .collect(),
+        }
+    }
+
+    pub fn include(self, namespace: &str, value: &str) -> SearchQuery {
+        self.namespaced(namespace, value, false)
+    }
+
+    pub fn exclude(self, namespace: &str, value: &str) -> SearchQuery {
+        self.namespaced(namespace, value, true)
+    }
+
+    /// Matches any of `values` in `namespace` (e.g. several languages).
+    pub fn include_any(mut self, namespace: &str, values: &[String]) -> SearchQuery {
+        let terms: Vec<QueryTerm> = values
+            .iter()
+            .filter_map(|value| namespaced_term(namespace, value, false))
+            .collect();
+        match terms.len() {
+            0 => {}
+            1 => self.terms.extend(terms),
+            _ => self.terms.push(QueryTerm::AnyOf(terms)),
+        }
+        self
+    }
+
+    pub fn extend(mut self, other: SearchQuery) -> SearchQuery {
+        self.terms.extend(other.terms);
+        self
+    }
+
+    pub fn render(&self) -> String {
+        self.terms
+            .iter()
+            .map(QueryTerm::render)
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    fn namespaced(mut self, namespace: &str, value: &str, exclude: bool) -> SearchQuery {
+        self.terms
+            .extend(namespaced_term(namespace, value, exclude));
+        self
+    }
+}
+
+fn namespaced_term(namespace: &str, value: &str, exclude: bool) -> Option<QueryTerm> {
+    let namespace = namespace.trim().to_ascii_lowercase();
+    let value = value.trim().trim_matches('"').trim();
+    if namespace.is_empty() || value.is_empty() {
+        return None;
+    }
+    Some(QueryTerm::Namespaced {
+        namespace,
+        value: value.to_string(),
+        exclude,
+    })
+}
+
+/// Quotes values with spaces or hyphens, so `non-h` is not read as an exclusion.
+fn quote(value: &str) -> String {
+    let value = value.replace('"', "");
+    if value.chars().any(|ch| ch.is_whitespace() || ch == '-') {
+        format!("\"{value}\"")
+    } else {
+        value
+    }
+}
+
+/// Splits on whitespace outside double quotes; quotes stay in the token.
+fn tokenize(query: &str) -> Vec<String> {
+    let mut tokens = Vec::new();
+    let mut current = String::new();
+    let mut quoted = false;
+    for ch in query.chars() {
+        match ch {
+            '"' => {
+                quoted = !quoted;
+                current.push(ch);
+            }
+            ch if ch.is_whitespace() && !quoted => {
+                if !current.is_empty() {
+                    tokens.push(std::mem::take(&mut current));
+                }
+            }
+            ch => current.push(ch),
+        }
+    }
+    if !current.is_empty() {
+        tokens.push(current);
+    }
+    tokens
+}
+
+fn parse_term(token: &str) -> QueryTerm {
+    let (exclude, body) = match token.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, token),
+    };
+    let Some((namespace, value)) = body.split_once(':') else {
+        return QueryTerm::Text(token.to_string());
+    };
+    let is_namespace = NAMESPACES
+        .iter()
+        .any(|known| known.eq_ignore_ascii_case(namespace));
+    if !is_namespace {
+        return QueryTerm::Text(token.to_string());
+    }
+
+    if !exclude {
+        let (comparison, amount) = Comparison::split(value);
+        match namespace.to_ascii_lowercase().as_str() {
+            "pages" => {
+                if let Ok(pages) = amount.parse() {
+                    return QueryTerm::Pages(comparison, pages);
+                }
+            }
+            "uploaded" => {
+                let mut chars = amount.chars();
+                let unit = chars.next_back().and_then(AgeUnit::from_suffix);
+                if let (Some(unit), Ok(amount)) = (unit, chars.as_str().parse()) {
+                    return QueryTerm::Uploaded(comparison, amount, unit);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    namespaced_term(namespace, value, exclude).unwrap_or_else(|| QueryTerm::Text(token.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_included_and_excluded_namespaced_terms() {
+        let mut query = SearchQuery::parse("soft").include("artist", "bai asuka");
+        for tag in ["netorare", "big breasts"] {
+            query.terms.push(QueryTerm::Namespaced {
+                namespace: "tag".to_string(),
+                value: tag.to_string(),
+                exclude: true,
+            });
+        }
+        assert_eq!(
+            query.render(),
+            r#"soft artist:"bai asuka" -tag:netorare -tag:"big breasts""#
+        );
+    }
+
+    #[test]
+    fn renders_page_and_upload_filters() {
+        let query = SearchQuery {
+            terms: vec![
+                QueryTerm::Text("soft".to_string()),
+                QueryTerm::Pages(Comparison::Greater, 20),
+                QueryTerm::Pages(Comparison::Less, 100),
+                QueryTerm::Uploaded(Comparison::Less, 7, AgeUnit::Days),
+            ],
+        };
+        assert_eq!(query.render(), "soft pages:>20 pages:<100 uploaded:<7d");
+    }
+
+    #[test]
+    fn include_any_joins_with_or() {
+        let languages = vec!["japanese".to_string(), "chinese".to_string()];
+        let query = SearchQuery::new().include_any("language", &languages);
+        assert_eq!(query.render(), "language:japanese OR language:chinese");
+        assert!(!SearchQuery::new()
+            .include_any("language", &languages[..1])
+            .render()
+            .contains("OR"));
+    }
+
+    #[test]
+    fn parse_keeps_quoted_values_together() {
+        let query = SearchQuery::parse(r#"my title -tag:"big breasts" pages:>=10 uploaded:<2w"#);
+        assert_eq!(
+            query.terms,
+            vec![
+                QueryTerm::Text("my".to_string()),
+                QueryTerm::Text("title".to_string()),
+                QueryTerm::Namespaced {
+                    namespace: "tag".to_string(),
+                    value: "big breasts".to_string(),
+                    exclude: true,
+                },
+                QueryTerm::Pages(Comparison::GreaterOrEqual, 10),
+                QueryTerm::Uploaded(Comparison::Less, 2, AgeUnit::Weeks),
+            ]
+        );
+        assert_eq!(
+            query.render(),
+            r#"my title -tag:"big breasts" pages:>=10 uploaded:<2w"#
+        );
+    }
+
+    #[test]
+    fn quotes_hyphenated_values() {
+        let query = SearchQuery::new().include("category", "non-h");
+        assert_eq!(query.render(), r#"category:"non-h""#);
+        assert_eq!(SearchQuery::parse(&query.render()), query);
+    }
+
+    #[test]
+    fn parse_leaves_non_terms_as_text() {
+        let query = SearchQuery::parse("re:zero 12:30 -yaoi");
+        assert_eq!(query.render(), "re:zero 12:30 -yaoi");
+        assert_eq!(query.terms[0], QueryTerm::Text("re:zero".to_string()));
+        assert_eq!(query.terms[1], QueryTerm::Text("12:30".to_string()));
+        assert_eq!(query.terms[2], QueryTerm::Text("-yaoi".to_string()));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::nhentai::{clean_title, NhentaiGallery};
+use crate::query::{QueryTerm, SearchQuery};
+
+/// Similarity from which a hit counts as the book itself rather than a lookalike.
+pub const NEAR_EXACT_SCORE: f64 = 0.9;
+
+/// Scale applied when the titles carry different numbers ("Soft Love 2" vs "Soft Love"),
+/// low enough that a sequel or another volume never reaches `NEAR_EXACT_SCORE`.
+const NUMBER_MISMATCH_FACTOR: f64 = 0.8;
+
+/// Comparable form of a title: full-width folded, bracketed tags stripped as in
+/// `clean_title`, lowercased, punctuation dropped.
+pub fn normalize_title(title: &str) -> String {
+    let folded: String = title.chars().map(fold_width).collect();
+    let stripped = clean_title(&folded);
+    // A title that is nothing but brackets still has to compare as something.
+    let source = if stripped.is_empty() {
+        folded
+    } else {
+        stripped
+    };
+    source
+        .to_lowercase()
+        .chars()
+        .map(|ch| if ch.is_alphanumeric() { ch } else { ' ' })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The free-text part of a search, without `tag:`, `pages:`... terms.
+pub fn search_text(search: &str) -> String {
+    SearchQuery::parse(search)
+        .terms
+        .into_iter()
+        .filter_map(|term| match term {
+            QueryTerm::Text(text) if !text.starts_with('-') => Some(text),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Similarity in `0.0..=1.0` of two titles, on their normalized forms. Character bigrams
+/// keep it meaningful for Japanese and Chinese titles, which have no word breaks; titles
+/// numbered differently (volumes, sequels) are scaled down by `NUMBER_MISMATCH_FACTOR`.
+pub fn title_similarity(a: &str, b: &str) -> f64 {
+    let a = normalize_title(a);
+    let b = normalize_title(b);
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    if a == b {
+        return 1.0;
+    }
+    let factor = if numbers(&a) == numbers(&b) {
+        1.0
+    } else {
+        NUMBER_MISMATCH_FACTOR
+    };
+    factor * bigram_similarity(&a, &b)
+}
+
+fn bigram_similarity(a: &str, b: &str) -> f64 {
+    let a_bigrams = bigrams(a);
+    let mut b_bigrams = bigrams(b);
+    let total = a_bigrams.len() + b_bigrams.len();
+    if total == 0 {
+        return 0.0;
+    }
+    let mut shared = 0;
+    for bigram in &a_bigrams {
+        if let Some(pos) = b_bigrams.iter().position(|other| other == bigram) {
+            b_bigrams.swap_remove(pos);
+            shared += 1;
+        }
+    }
+    (2 * shared) as f64 / total as f64
+}
+
+/// Best similarity of `name` to any of the gallery's titles, alternates included.
+pub fn gallery_similarity(gallery: &NhentaiGallery, name: &str) -> f64 {
+    std::iter::once(&gallery.title)
+        .chain(&gallery.alternate_titles)
+        .map(|title| title_similarity(title, name))
+        .fold(0.0, f64::max)
+}
+
+pub fn is_near_exact(gallery: &NhentaiGallery, name: &str) -> bool {
+    gallery_similarity(gallery, name) >= NEAR_EXACT_SCORE
+}
+
+/// Orders `galleries` best match for `name` first, the most favorited first among equal
+/// scores; full ties keep the site's order.
+pub fn rank_by_title(galleries: Vec<NhentaiGallery>, name: &str) -> Vec<NhentaiGallery> {
+    let mut ranked: Vec<(NhentaiGallery, f64)> = galleries
+        .into_iter()
+        .map(|gallery| {
+            let score = gallery_similarity(&gallery, name);
+            (gallery, score)
+        })
+        .collect();
+    ranked.sort_by(|a, b| {
+        b.1.total_cmp(&a.1)
+            .then_with(|| b.0.favorites.unwrap_or(0).cmp(&a.0.favorites.unwrap_or(0)))
+    });
+    ranked.into_iter().map(|(gallery, _)| gallery).collect()
+}
+
+/// Volume, part and sequel numbers of a normalized title: digit runs and Roman numerals
+/// from II up, so `Soft Love II` and `Soft Love 2` agree.
+fn numbers(normalized: &str) -> Vec<u64> {
+    let mut out: Vec<u64> = normalized
+        .split(|ch: char| !ch.is_ascii_digit())
+        .filter_map(|digits| digits.parse().ok())
+        .collect();
+    out.extend(normalized.split_whitespace().filter_map(|word| match word {
+        "ii" => Some(2),
+        "iii" => Some(3),
+        "iv" => Some(4),
+        "vi" => Some(6),
+        "vii" => Some(7),
+        "viii" => Some(8),
+        "ix" => Some(9),
+        _ => None,
+    }));
+    out.sort_unstable();
+    out
+}
+
+fn bigrams(value: &str) -> Vec<(char, char)> {
+    let chars: Vec<char> = value.chars().filter(|ch| !ch.is_whitespace()).collect();
+    if chars.len() == 1 {
+        return vec![(chars[0], chars[0])];
+    }
+    chars.windows(2).map(|pair| (pair[0], pair[1])).collect()
+}
+
+fn fold_width(ch: char) -> char {
+    match ch {
+        '\u{3000}' => ' ',
+        '\u{FF01}'..='\u{FF5E}' => char::from_u32(ch as u32 - 0xFEE0).unwrap_or(ch),
+        _ => ch,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalize_title_folds_width_case_brackets_and_punctuation() {
+        assert_eq!(
+            normalize_title("(C99) [Circle (Artist)] Ｓｏｆｔ　Ｌｏｖｅ!! ～Part 2～ [English]"),
+            "soft love part 2"
+        );
+        assert_eq!(normalize_title("[English]"), "english");
+    }
+
+    #[test]
+    fn search_text_drops_query_terms() {
+        assert_eq!(
+            search_text(r#"soft love tag:"full color" pages:>20 -yaoi"#),
+            "soft love"
+        );
+    }
+
+    #[test]
+    fn title_similarity_scores_near_exact_titles_high() {
+        assert_eq!(
+            title_similarity("Soft Love", "[Circle] SOFT LOVE! [English]"),
+            1.0
+        );
+        assert!(title_similarity("Soft Love 2", "Soft Love") < 0.8);
+        assert!(title_similarity("Soft Love Vol. 2", "Soft Love vol 3") < NEAR_EXACT_SCORE);
+        assert_eq!(numbers("soft love ii"), numbers("soft love 2"));
+        assert!(title_similarity("Hard Days", "Soft Love") < 0.3);
+        assert_eq!(title_similarity("", "Soft Love"), 0.0);
+    }
+
+    #[test]
+    fn rank_by_title_puts_best_match_first_and_keeps_ties_in_order() {
+        let gallery = |title: &str| NhentaiGallery {
+            title: title.to_string(),
+            ..Default::default()
+        };
+        let ranked = rank_by_title(
+            vec![
+                gallery("Other Story"),
+                gallery("Soft Love 2"),
+                gallery("Soft Love"),
+                gallery("Another Story"),
+            ],
+            "soft love",
+        );
+        let titles: Vec<&str> = ranked.iter().map(|g| g.title.as_str()).collect();
+        assert_eq!(titles[..2], ["Soft Love", "Soft Love 2"]);
+        assert!(is_near_exact(&ranked[0], "soft love"));
+        assert!(!is_near_exact(&gallery("Other Story"), "soft love"));
+
+        let japanese = NhentaiGallery {
+            alternate_titles: vec!["やわらかい恋".to_string()],
+            ..gallery("Soft Love")
+        };
+        assert!(is_near_exact(&japanese, "やわらかい恋"));
+    }
+
+    #[test]
+    fn rank_by_title_breaks_ties_by_favorites() {
+        let gallery = |id: &str, favorites: Option<u32>| NhentaiGallery {
+            id: Some(id.to_string()),
+            title: "Soft Love".to_string(),
+            favorites,
+            ..Default::default()
+        };
+        let ranked = rank_by_title(
+            vec![
+                gallery("1", None),
+                gallery("2", Some(10)),
+                gallery("3", Some(500)),
+                gallery("4", Some(10)),
+            ],
+            "soft love",
+        );
+        let ids: Vec<&str> = ranked.iter().filter_map(|g| g.id.as_deref()).collect();
+        assert_eq!(ids, ["3", "2", "4", "1"]);
+    }
+}
@@ -0,0 +1,162 @@
+use std::collections::HashMap;
+
+use crate::date::parse_http_date;
+
+const BASE_DELAY_MS: u64 = 500;
+const MAX_DELAY_MS: u64 = 30_000;
+
+/// Whether `wait_ms` really waits. wasm32-unknown-unknown has neither a clock nor
+/// `sleep` and the host exposes no timer, so inside the plugin there is no backoff and
+/// `should_retry` only lets server errors through.
+pub const CAN_WAIT: bool = cfg!(not(target_arch = "wasm32"));
+
+pub fn is_retryable_status(status: u16) -> bool {
+    matches!(status, 408 | 425 | 429 | 500 | 502 | 503 | 504)
+}
+
+/// Whether an HTTP error is worth another attempt, given whether we can back off first.
+pub fn should_retry(status: u16, retry_after_ms: Option<u64>) -> bool {
+    should_retry_when(status, retry_after_ms, CAN_WAIT)
+}
+
+fn should_retry_when(status: u16, retry_after_ms: Option<u64>, can_wait: bool) -> bool {
+    if can_wait {
+        return is_retryable_status(status);
+    }
+    // An immediate retry only helps with a flaky server. A rate limit, or any status the
+    // server attached a wait to, would just be hit again and extend the ban.
+    is_retryable_status(status) && status >= 500 && retry_after_ms.is_none_or(|ms| ms == 0)
+}
+
+/// Reads a `Retry-After` header in milliseconds. An HTTP-date is measured against the
+/// response's own `Date` header, since the plugin has no wall clock; without one it is
+/// ignored.
+pub fn parse_retry_after(headers: &HashMap<String, String>) -> Option<u64> {
+    let header = |wanted: &str| {
+        headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(wanted))
+            .map(|(_, value)| value.trim())
+    };
+    let value = header("retry-after")?;
+    let seconds = match value.parse::<u64>() {
+        Ok(seconds) => seconds,
+        Err(_) => {
+            let retry_at = parse_http_date(value)?;
+            let now = parse_http_date(header("date")?)?;
+            u64::try_from(retry_at - now).unwrap_or(0)
+        }
+    };
+    Some(seconds.saturating_mul(1000))
+}
+
+/// Delay before retry number `attempt` (1-based): exponential backoff with jitter,
+/// or the server's `Retry-After` when it asks for longer. Capped at 30 seconds.
+pub fn retry_delay_ms(attempt: u32, retry_after_ms: Option<u64>, seed: &str) -> u64 {
+    let exponent = attempt.saturating_sub(1).min(16);
+    let backoff = BASE_DELAY_MS.saturating_mul(1 << exponent);
+    let jitter = jitter_ms(seed, attempt, backoff / 2);
+    let delay = backoff.saturating_add(jitter);
+
+    retry_after_ms
+        .map_or(delay, |server| server.max(delay))
+        .min(MAX_DELAY_MS)
+}
+
+/// Sleeps for `delay_ms`; a no-op where `CAN_WAIT` is false.
+pub fn wait_ms(delay_ms: u64) {
+    #[cfg(not(target_arch = "wasm32"))]
+    std::thread::sleep(std::time::Duration::from_millis(delay_ms));
+
+    #[cfg(target_arch = "wasm32")]
+    let _ = delay_ms;
+}
+
+/// Deterministic jitter in `0..=max` derived from the URL and attempt, so concurrent
+/// lookups of different pages do not retry in lockstep (there is no RNG in the sandbox).
+fn jitter_ms(seed: &str, attempt: u32, max: u64) -> u64 {
+    if max == 0 {
+        return 0;
+    }
+    let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+    for byte in seed.bytes().chain(attempt.to_le_bytes()) {
+        hash ^= u64::from(byte);
+        hash = hash.wrapping_mul(0x0100_0000_01b3);
+    }
+    hash % (max + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retries_rate_limits_and_server_errors_only() {
+        assert!(is_retryable_status(429));
+        assert!(is_retryable_status(503));
+        assert!(!is_retryable_status(404));
+        assert!(!is_retryable_status(403));
+    }
+
+    #[test]
+    fn without_a_timer_only_server_errors_retry() {
+        assert!(should_retry_when(429, None, true));
+        assert!(should_retry_when(503, Some(5_000), true));
+
+        assert!(!should_retry_when(429, None, false));
+        assert!(!should_retry_when(408, None, false));
+        assert!(should_retry_when(503, None, false));
+        assert!(should_retry_when(502, Some(0), false));
+        assert!(!should_retry_when(503, Some(5_000), false));
+        assert!(!should_retry_when(404, None, false));
+    }
+
+    #[test]
+    fn parse_retry_after_reads_seconds_case_insensitively() {
+        let mut headers = HashMap::new();
+        headers.insert("retry-after".to_string(), " 7 ".to_string());
+        assert_eq!(parse_retry_after(&headers), Some(7000));
+
+        let mut headers = HashMap::new();
+        headers.insert(
+            "Retry-After".to_string(),
+            "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        );
+        assert_eq!(parse_retry_after(&headers), None);
+    }
+
+    #[test]
+    fn parse_retry_after_measures_http_date_against_date_header() {
+        let mut headers = HashMap::new();
+        headers.insert(
+            "Retry-After".to_string(),
+            "Wed, 21 Oct 2015 07:28:00 GMT".to_string(),
+        );
+        headers.insert(
+            "Date".to_string(),
+            "Wed, 21 Oct 2015 07:27:45 GMT".to_string(),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(15_000));
+
+        headers.insert(
+            "Date".to_string(),
+            "Wed, 21 Oct 2015 07:30:00 GMT".to_string(),
+        );
+        assert_eq!(parse_retry_after(&headers), Some(0));
+    }
+
+    #[test]
+    fn retry_delay_grows_exponentially_with_bounded_jitter() {
+        let first = retry_delay_ms(1, None, "https://nhentai.net/g/1/");
+        let third = retry_delay_ms(3, None, "https://nhentai.net/g/1/");
+        assert!((500..=750).contains(&first), "first delay was {first}");
+        assert!((2000..=3000).contains(&third), "third delay was {third}");
+        assert_eq!(retry_delay_ms(20, None, "x"), 30_000);
+    }
+
+    #[test]
+    fn retry_delay_honours_longer_retry_after() {
+        assert_eq!(retry_delay_ms(1, Some(5000), "x"), 5000);
+        assert_eq!(retry_delay_ms(1, Some(120_000), "x"), 30_000);
+    }
+}
@@ -0,0 +1,159 @@
+use rs_plugin_common_interfaces::PluginCredential;
+use serde_json::Value;
+
+/// nhentai browser session taken from the plugin credential: the cookies that prove a
+/// logged-in (or challenge-cleared) browser, and the User-Agent they were issued for.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Session {
+    /// Value for the `Cookie` header, e.g. `sessionid=...; cf_clearance=...`.
+    pub cookie: Option<String>,
+    /// Cloudflare binds `cf_clearance` to the browser's User-Agent, so it must be replayed.
+    pub user_agent: Option<String>,
+}
+
+impl Session {
+    /// Reads the credential `settings` object (`cookie`, `sessionid`, `cf_clearance`,
+    /// `csrftoken`, `user_agent`). A bare `password` is accepted as the session cookie,
+    /// or as a full cookie header when it contains `=`.
+    pub fn from_credential(credential: &PluginCredential) -> Option<Session> {
+        let settings = &credential.settings;
+        let mut pairs: Vec<(String, String)> = Vec::new();
+
+        let raw_cookie = setting(settings, &["cookie", "cookies"]).or_else(|| {
+            credential
+                .password
+                .as_deref()
+                .map(str::trim)
+                .filter(|p| p.contains('='))
+        });
+        if let Some(raw) = raw_cookie {
+            for part in raw.split(';') {
+                if let Some((name, value)) = part.split_once('=') {
+                    push_cookie(&mut pairs, name, value);
+                }
+            }
+        }
+
+        let session_id = setting(settings, &["sessionid", "session_id", "session"]).or_else(|| {
+            credential
+                .password
+                .as_deref()
+                .map(str::trim)
+                .filter(|p| !p.is_empty() && !p.contains('='))
+        });
+        if let Some(value) = session_id {
+            push_cookie(&mut pairs, "sessionid", value);
+        }
+        if let Some(value) = setting(settings, &["cf_clearance", "cfClearance"]) {
+            push_cookie(&mut pairs, "cf_clearance", value);
+        }
+        if let Some(value) = setting(settings, &["csrftoken", "csrf_token"]) {
+            push_cookie(&mut pairs, "csrftoken", value);
+        }
+
+        let cookie = if pairs.is_empty() {
+            None
+        } else {
+            Some(
+                pairs
+                    .iter()
+                    .map(|(name, value)| format!("{name}={value}"))
+                    .collect::<Vec<_>>()
+                    .join("; "),
+            )
+        };
+        let user_agent =
+            setting(settings, &["user_agent", "userAgent", "user-agent"]).map(str::to_string);
+
+        if cookie.is_none() && user_agent.is_none() {
+            None
+        } else {
+            Some(Session { cookie, user_agent })
+        }
+    }
+}
+
+fn setting<'a>(settings: &'a Value, keys: &[&str]) -> Option<&'a str> {
+    keys.iter()
+        .filter_map(|key| settings.get(key).and_then(Value::as_str))
+        .map(str::trim)
+        .find(|value| !value.is_empty())
+}
+
+/// Adds or replaces a cookie, so explicit fields win over the raw cookie string.
+fn push_cookie(pairs: &mut Vec<(String, String)>, name: &str, value: &str) {
+    let name = name.trim();
+    let value = value.trim();
+    if name.is_empty() || value.is_empty() {
+        return;
+    }
+    match pairs.iter_mut().find(|(existing, _)| existing == name) {
+        Some(pair) => pair.1 = value.to_string(),
+        None => pairs.push((name.to_string(), value.to_string())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn builds_cookie_header_from_settings_fields() {
+        let credential = PluginCredential {
+            settings: json!({
+                "sessionid": "abc",
+                "cf_clearance": "xyz",
+                "user_agent": "Mozilla/5.0 Test"
+            }),
+            ..Default::default()
+        };
+
+        let session = Session::from_credential(&credential).expect("session");
+        assert_eq!(
+            session.cookie.as_deref(),
+            Some("sessionid=abc; cf_clearance=xyz")
+        );
+        assert_eq!(session.user_agent.as_deref(), Some("Mozilla/5.0 Test"));
+    }
+
+    #[test]
+    fn explicit_fields_override_raw_cookie() {
+        let credential = PluginCredential {
+            settings: json!({ "cookie": "csrftoken=t; cf_clearance=old", "cf_clearance": "new" }),
+            ..Default::default()
+        };
+
+        let session = Session::from_credential(&credential).expect("session");
+        assert_eq!(
+            session.cookie.as_deref(),
+            Some("csrftoken=t; cf_clearance=new")
+        );
+        assert!(session.user_agent.is_none());
+    }
+
+    #[test]
+    fn password_is_used_as_session_cookie() {
+        let credential = PluginCredential {
+            password: Some("abc".to_string()),
+            ..Default::default()
+        };
+        let session = Session::from_credential(&credential).expect("session");
+        assert_eq!(session.cookie.as_deref(), Some("sessionid=abc"));
+
+        let credential = PluginCredential {
+            password: Some("sessionid=abc; cf_clearance=xyz".to_string()),
+            ..Default::default()
+        };
+        let session = Session::from_credential(&credential).expect("session");
+        assert_eq!(
+            session.cookie.as_deref(),
+            Some("sessionid=abc; cf_clearance=xyz")
+        );
+    }
+
+    #[test]
+    fn empty_credential_yields_no_session() {
+        assert!(Session::from_credential(&PluginCredential::default()).is_none());
+    }
+}
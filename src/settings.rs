@@ -0,0 +1,273 @@
+use rs_plugin_common_interfaces::{lookup::RsLookupWrapper, CustomParamTypes};
+
+use crate::blacklist::Blacklist;
+use crate::cache::{ResponseCache, DEFAULT_CACHE_MAX_ENTRIES, DEFAULT_CACHE_TTL_CALLS};
+use crate::image_hosts::{ImageHostStrategy, ImageHosts};
+use crate::language::LanguageFilter;
+use crate::nhentai::{normalize_base_url, SearchSort, DEFAULT_BASE_URL};
+use crate::session::Session;
+use crate::transport::Transport;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Backend {
+    #[default]
+    Html,
+    Api,
+    Auto,
+}
+
+impl Backend {
+    pub fn parse(value: &str) -> Option<Backend> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "html" => Some(Backend::Html),
+            "api" | "json" => Some(Backend::Api),
+            "auto" => Some(Backend::Auto),
+            _ => None,
+        }
+    }
+}
+
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+pub const DEFAULT_RANDOM_REROLLS: u32 = 5;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Settings {
+    pub custom_search_params: Option<String>,
+    pub blacklist: Blacklist,
+    pub language: LanguageFilter,
+    pub sort: SearchSort,
+    pub backend: Backend,
+    pub max_retries: u32,
+    /// How many search hits get their gallery page fetched; 0 disables enrichment.
+    pub enrich_limit: usize,
+    /// Extra `/random/` draws when a gallery fails the blacklist or language filter.
+    pub random_rerolls: u32,
+    pub base_url: String,
+    pub image_hosts: ImageHosts,
+    pub cache: ResponseCache,
+    pub session: Option<Session>,
+    /// Comes from the plugin config rather than lookup params; see `Transport::from_config`.
+    pub transport: Transport,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            custom_search_params: None,
+            blacklist: Blacklist::default(),
+            language: LanguageFilter::default(),
+            sort: SearchSort::default(),
+            backend: Backend::default(),
+            max_retries: DEFAULT_MAX_RETRIES,
+            enrich_limit: 0,
+            random_rerolls: DEFAULT_RANDOM_REROLLS,
+            base_url: DEFAULT_BASE_URL.to_string(),
+            image_hosts: ImageHosts::default(),
+            cache: ResponseCache::default(),
+            session: None,
+            transport: Transport::Live,
+        }
+    }
+}
+
+impl Settings {
+    pub fn from_lookup(lookup: &RsLookupWrapper) -> Settings {
+        Settings {
+            custom_search_params: text_param(lookup, "custom_search_params").map(str::to_string),
+            blacklist: Blacklist::parse(text_param(lookup, "blacklist").unwrap_or_default()),
+            language: text_param(lookup, "language")
+                .and_then(LanguageFilter::parse)
+                .unwrap_or_default(),
+            sort: text_param(lookup, "sort")
+                .and_then(SearchSort::parse)
+                .unwrap_or_default(),
+            backend: text_param(lookup, "backend")
+                .and_then(Backend::parse)
+                .unwrap_or_default(),
+            max_retries: uint_param(lookup, "max_retries")
+                .and_then(|v| u32::try_from(v).ok())
+                .unwrap_or(DEFAULT_MAX_RETRIES),
+            enrich_limit: uint_param(lookup, "enrich_results")
+                .and_then(|v| usize::try_from(v).ok())
+                .unwrap_or(0),
+            random_rerolls: uint_param(lookup, "random_rerolls")
+                .and_then(|v| u32::try_from(v).ok())
+                .unwrap_or(DEFAULT_RANDOM_REROLLS),
+            base_url: normalize_base_url(
+                text_param(lookup, "base_url").unwrap_or(DEFAULT_BASE_URL),
+            ),
+            image_hosts: ImageHosts::parse(
+                text_param(lookup, "image_hosts").unwrap_or_default(),
+                text_param(lookup, "image_host_strategy")
+                    .and_then(ImageHostStrategy::parse)
+                    .unwrap_or_default(),
+            ),
+            cache: ResponseCache {
+                ttl_calls: uint_param(lookup, "cache_ttl").unwrap_or(DEFAULT_CACHE_TTL_CALLS),
+                max_entries: uint_param(lookup, "cache_max_entries")
+                    .and_then(|v| usize::try_from(v).ok())
+                    .unwrap_or(DEFAULT_CACHE_MAX_ENTRIES),
+            },
+            session: lookup
+                .credential
+                .as_ref()
+                .and_then(Session::from_credential),
+            transport: Transport::Live,
+        }
+    }
+}
+
+fn text_param<'a>(lookup: &'a RsLookupWrapper, name: &str) -> Option<&'a str> {
+    lookup
+        .params
+        .as_ref()
+        .and_then(|p| p.get(name))
+        .and_then(|s| match s {
+            CustomParamTypes::Text(v) | CustomParamTypes::Url(v) => v.as_deref(),
+            _ => None,
+        })
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+fn uint_param(lookup: &RsLookupWrapper, name: &str) -> Option<u64> {
+    match lookup.params.as_ref()?.get(name)? {
+        CustomParamTypes::UInteger(v) => *v,
+        CustomParamTypes::Integer(v) => v.and_then(|v| u64::try_from(v).ok()),
+        CustomParamTypes::Text(v) => v.as_deref().and_then(|v| v.trim().parse().ok()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rs_plugin_common_interfaces::lookup::RsLookupQuery;
+    use std::collections::HashMap;
+
+    fn lookup_with(params: &[(&str, &str)]) -> RsLookupWrapper {
+        let params = params
+            .iter()
+            .map(|(k, v)| (k.to_string(), CustomParamTypes::Text(Some(v.to_string()))))
+            .collect::<HashMap<_, _>>();
+        RsLookupWrapper {
+            query: RsLookupQuery::Book(Default::default()),
+            credential: None,
+            params: Some(params),
+        }
+    }
+
+    #[test]
+    fn defaults_to_html_backend() {
+        let settings = Settings::from_lookup(&lookup_with(&[]));
+        assert_eq!(settings.backend, Backend::Html);
+        assert!(settings.custom_search_params.is_none());
+        assert_eq!(settings.max_retries, DEFAULT_MAX_RETRIES);
+        assert_eq!(settings.base_url, "https://nhentai.net");
+    }
+
+    #[test]
+    fn reads_image_hosts_and_strategy() {
+        let settings = Settings::from_lookup(&lookup_with(&[
+            ("image_hosts", "i1.nhentai.net,i2.nhentai.net"),
+            ("image_host_strategy", "round-robin"),
+        ]));
+        assert_eq!(settings.image_hosts.hosts.len(), 2);
+        assert_eq!(settings.image_hosts.strategy, ImageHostStrategy::RoundRobin);
+    }
+
+    #[test]
+    fn normalizes_configured_base_url() {
+        let settings =
+            Settings::from_lookup(&lookup_with(&[("base_url", "http://localhost:8080/")]));
+        assert_eq!(settings.base_url, "http://localhost:8080");
+
+        let settings = Settings::from_lookup(&lookup_with(&[("base_url", "mirror.example")]));
+        assert_eq!(settings.base_url, "https://mirror.example");
+    }
+
+    #[test]
+    fn reads_max_retries_from_number_or_text() {
+        let mut lookup = lookup_with(&[]);
+        lookup.params.as_mut().unwrap().insert(
+            "max_retries".to_string(),
+            CustomParamTypes::UInteger(Some(0)),
+        );
+        assert_eq!(Settings::from_lookup(&lookup).max_retries, 0);
+
+        let settings = Settings::from_lookup(&lookup_with(&[("max_retries", "5")]));
+        assert_eq!(settings.max_retries, 5);
+    }
+
+    #[test]
+    fn reads_language_filter() {
+        let settings = Settings::from_lookup(&lookup_with(&[]));
+        assert_eq!(settings.language, LanguageFilter::default());
+
+        let settings = Settings::from_lookup(&lookup_with(&[("language", "japanese,chinese")]));
+        assert_eq!(
+            settings.language,
+            LanguageFilter::Only(vec!["japanese".to_string(), "chinese".to_string()])
+        );
+    }
+
+    #[test]
+    fn reads_sort_order() {
+        assert_eq!(
+            Settings::from_lookup(&lookup_with(&[])).sort,
+            SearchSort::Recent
+        );
+        let settings = Settings::from_lookup(&lookup_with(&[("sort", "popular-month")]));
+        assert_eq!(settings.sort, SearchSort::PopularMonth);
+    }
+
+    #[test]
+    fn reads_enrichment_cap() {
+        assert_eq!(Settings::from_lookup(&lookup_with(&[])).enrich_limit, 0);
+        let settings = Settings::from_lookup(&lookup_with(&[("enrich_results", "5")]));
+        assert_eq!(settings.enrich_limit, 5);
+    }
+
+    #[test]
+    fn reads_cache_bounds() {
+        let settings = Settings::from_lookup(&lookup_with(&[]));
+        assert_eq!(settings.cache, ResponseCache::default());
+
+        let settings = Settings::from_lookup(&lookup_with(&[
+            ("cache_ttl", "0"),
+            ("cache_max_entries", "4"),
+        ]));
+        assert_eq!(settings.cache.ttl_calls, 0);
+        assert_eq!(settings.cache.max_entries, 4);
+    }
+
+    #[test]
+    fn reads_backend_and_custom_params() {
+        let settings = Settings::from_lookup(&lookup_with(&[
+            ("backend", " Auto "),
+            ("custom_search_params", "-yaoi"),
+        ]));
+        assert_eq!(settings.backend, Backend::Auto);
+        assert_eq!(settings.custom_search_params.as_deref(), Some("-yaoi"));
+    }
+
+    #[test]
+    fn reads_session_from_credential() {
+        let mut lookup = lookup_with(&[]);
+        assert!(Settings::from_lookup(&lookup).session.is_none());
+
+        lookup.credential = Some(rs_plugin_common_interfaces::PluginCredential {
+            settings: serde_json::json!({ "cf_clearance": "xyz", "user_agent": "UA" }),
+            ..Default::default()
+        });
+        let session = Settings::from_lookup(&lookup).session.expect("session");
+        assert_eq!(session.cookie.as_deref(), Some("cf_clearance=xyz"));
+        assert_eq!(session.user_agent.as_deref(), Some("UA"));
+    }
+
+    #[test]
+    fn unknown_backend_falls_back_to_html() {
+        let settings = Settings::from_lookup(&lookup_with(&[("backend", "graphql")]));
+        assert_eq!(settings.backend, Backend::Html);
+    }
+}
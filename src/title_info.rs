@@ -0,0 +1,160 @@
+use crate::language::language_marker;
+
+/// What nhentai's title conventions put in brackets around the name:
+/// `(C99) [Circle (Artist)] Title (Parody) [English] [Translator] [Digital]`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TitleInfo {
+    /// Convention the work was released at, e.g. `C99` or `COMIC1☆15`.
+    pub event: Option<String>,
+    /// Circle, or the artist when the prefix has no `(Artist)` part.
+    pub circle: Option<String>,
+    pub translator: Option<String>,
+    pub digital: bool,
+    pub decensored: bool,
+    pub colorized: bool,
+    pub ongoing: bool,
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment<'a> {
+    Paren(&'a str),
+    Bracket(&'a str),
+    Text,
+}
+
+/// Reads the bracketed segments of a raw (not `clean_title`d) gallery title.
+pub fn parse_title_info(raw_title: &str) -> TitleInfo {
+    let segments = split_segments(raw_title);
+    let mut info = TitleInfo::default();
+    let mut rest = segments.as_slice();
+
+    if let [Segment::Paren(event), tail @ ..] = rest {
+        info.event = non_empty(event);
+        rest = tail;
+    }
+    match rest {
+        [Segment::Bracket(prefix), tail @ ..] if language_marker(prefix).is_none() => {
+            let circle = prefix.split('(').next().unwrap_or(prefix);
+            info.circle = non_empty(circle).or_else(|| non_empty(prefix));
+            rest = tail;
+        }
+        _ => {}
+    }
+
+    // Only segments after the title text are markers; a leading bracket is the circle.
+    let title_end = rest.iter().position(|s| *s == Segment::Text);
+    let trailing = title_end.map_or(rest, |idx| &rest[idx..]);
+    for segment in trailing {
+        let (Segment::Bracket(content) | Segment::Paren(content)) = segment else {
+            continue;
+        };
+        if language_marker(content).is_some() {
+            continue;
+        }
+        match content.trim().to_lowercase().as_str() {
+            "digital" | "デジタル版" | "dl版" => info.digital = true,
+            "decensored" | "uncensored" | "無修正" => info.decensored = true,
+            "colorized" | "colorised" | "カラー化" => info.colorized = true,
+            "ongoing" | "on-going" | "wip" | "進行中" | "連載中" => info.ongoing = true,
+            _ => {
+                // Parodies go in parentheses and are already read from the tags.
+                if matches!(segment, Segment::Bracket(_)) && info.translator.is_none() {
+                    info.translator = non_empty(content);
+                }
+            }
+        }
+    }
+
+    info
+}
+
+/// Top-level `(...)`, `[...]` and text runs; brackets nested inside a segment stay in it.
+fn split_segments(title: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    let mut depth = 0usize;
+    let mut open: Option<(char, usize)> = None;
+    let mut text_start: Option<usize> = None;
+
+    for (idx, ch) in title.char_indices() {
+        match (ch, open) {
+            ('(' | '[', None) => {
+                if let Some(start) = text_start.take() {
+                    if !title[start..idx].trim().is_empty() {
+                        segments.push(Segment::Text);
+                    }
+                }
+                open = Some((ch, idx + ch.len_utf8()));
+                depth = 1;
+            }
+            ('(' | '[', Some(_)) => depth += 1,
+            (')' | ']', Some((kind, start))) => {
+                depth -= 1;
+                if depth == 0 {
+                    let content = &title[start..idx];
+                    segments.push(if kind == '(' {
+                        Segment::Paren(content)
+                    } else {
+                        Segment::Bracket(content)
+                    });
+                    open = None;
+                }
+            }
+            (_, None) if text_start.is_none() && !ch.is_whitespace() => text_start = Some(idx),
+            _ => {}
+        }
+    }
+    if text_start.is_some_and(|start| !title[start..].trim().is_empty()) {
+        segments.push(Segment::Text);
+    }
+
+    segments
+}
+
+fn non_empty(value: &str) -> Option<String> {
+    let value = value.split_whitespace().collect::<Vec<_>>().join(" ");
+    if value.is_empty() {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_full_convention() {
+        let info = parse_title_info(
+            "(C99) [Circle Name (Artist Name)] Soft Love (Original) [English] [Some Scans] [Digital] [Decensored]",
+        );
+        assert_eq!(
+            info,
+            TitleInfo {
+                event: Some("C99".to_string()),
+                circle: Some("Circle Name".to_string()),
+                translator: Some("Some Scans".to_string()),
+                digital: true,
+                decensored: true,
+                colorized: false,
+                ongoing: false,
+            }
+        );
+    }
+
+    #[test]
+    fn reads_artist_only_prefix_and_japanese_markers() {
+        let info = parse_title_info("[アーティスト] タイトル [中国翻訳] [カラー化] [進行中]");
+        assert_eq!(info.event, None);
+        assert_eq!(info.circle, Some("アーティスト".to_string()));
+        assert_eq!(info.translator, None);
+        assert!(info.colorized);
+        assert!(info.ongoing);
+    }
+
+    #[test]
+    fn plain_title_has_no_info() {
+        assert_eq!(parse_title_info("Soft Love"), TitleInfo::default());
+        assert_eq!(parse_title_info(""), TitleInfo::default());
+    }
+}
@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use extism_pdk::{config, http, log, HttpRequest, LogLevel};
+use serde_json::{json, Value};
+
+/// Plugin config key (manifest `config`, not lookup params) selecting the transport.
+pub const TRANSPORT_CONFIG_KEY: &str = "nh_transport";
+/// Plugin config key holding the replay fixtures as a JSON object keyed by URL.
+pub const FIXTURES_CONFIG_KEY: &str = "nh_fixtures";
+/// Prefix of the log lines written in record mode; the rest of the line is JSON.
+pub const RECORD_LOG_PREFIX: &str = "nhentai-record ";
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RecordedResponse {
+    pub status: u16,
+    pub headers: HashMap<String, String>,
+    pub body: String,
+}
+
+/// How HTTP requests leave the plugin. Replay serves captured responses so the
+/// lookup flows can run offline; record performs live requests and logs each
+/// response in the fixture format.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Transport {
+    #[default]
+    Live,
+    Record,
+    Replay(HashMap<String, RecordedResponse>),
+}
+
+impl Transport {
+    pub fn from_config() -> Result<Transport, extism_pdk::Error> {
+        let mode = config::get(TRANSPORT_CONFIG_KEY)?.unwrap_or_default();
+        match mode.trim().to_ascii_lowercase().as_str() {
+            "" | "live" => Ok(Transport::Live),
+            "record" => Ok(Transport::Record),
+            "replay" => {
+                let fixtures = config::get(FIXTURES_CONFIG_KEY)?.unwrap_or_default();
+                parse_fixtures(&fixtures)
+                    .map(Transport::Replay)
+                    .ok_or_else(|| extism_pdk::Error::msg("Invalid nh_fixtures replay config"))
+            }
+            other => Err(extism_pdk::Error::msg(format!(
+                "Unknown nh_transport: {other}"
+            ))),
+        }
+    }
+
+    pub fn send(&self, request: &HttpRequest) -> Result<RecordedResponse, extism_pdk::Error> {
+        match self {
+            Transport::Live => send_live(request),
+            Transport::Record => {
+                let response = send_live(request)?;
+                log!(
+                    LogLevel::Info,
+                    "{}{}",
+                    RECORD_LOG_PREFIX,
+                    record_line(&request.url, &response)
+                );
+                Ok(response)
+            }
+            Transport::Replay(fixtures) => fixtures.get(&request.url).cloned().ok_or_else(|| {
+                extism_pdk::Error::msg(format!("No recorded response for {}", request.url))
+            }),
+        }
+    }
+}
+
+fn send_live(request: &HttpRequest) -> Result<RecordedResponse, extism_pdk::Error> {
+    let res = http::request::<Vec<u8>>(request, None)?;
+    Ok(RecordedResponse {
+        status: res.status_code(),
+        headers: res.headers().clone(),
+        body: String::from_utf8_lossy(&res.body()).to_string(),
+    })
+}
+
+/// Parses `{"<url>": {"status": 200, "headers": {...}, "body": "..."}}`. A plain string
+/// value is shorthand for a 200 response with that body.
+pub fn parse_fixtures(json: &str) -> Option<HashMap<String, RecordedResponse>> {
+    if json.trim().is_empty() {
+        return Some(HashMap::new());
+    }
+
+    let value: Value = serde_json::from_str(json).ok()?;
+    value
+        .as_object()?
+        .iter()
+        .map(|(url, entry)| Some((url.clone(), parse_fixture(entry)?)))
+        .collect()
+}
+
+fn parse_fixture(entry: &Value) -> Option<RecordedResponse> {
+    if let Some(body) = entry.as_str() {
+        return Some(RecordedResponse {
+            status: 200,
+            body: body.to_string(),
+            ..Default::default()
+        });
+    }
+
+    let status = entry.get("status").map_or(Some(200), |s| {
+        s.as_u64().and_then(|s| u16::try_from(s).ok())
+    })?;
+    let headers = entry
+        .get("headers")
+        .and_then(Value::as_object)
+        .map(|headers| {
+            headers
+                .iter()
+                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                .collect()
+        })
+        .unwrap_or_default();
+    let body = entry
+        .get("body")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    Some(RecordedResponse {
+        status,
+        headers,
+        body,
+    })
+}
+
+/// One fixture entry as a single JSON line, so recordings can be merged into a fixture file.
+pub fn record_line(url: &str, response: &RecordedResponse) -> String {
+    json!({
+        "url": url,
+        "status": response.status,
+        "headers": response.headers,
+        "body": response.body,
+    })
+    .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_fixtures_accepts_full_and_shorthand_entries() {
+        let fixtures = parse_fixtures(
+            r#"{
+                "https://nhentai.net/g/1/": "<html>gallery</html>",
+                "https://nhentai.net/g/2/": {"status": 404, "headers": {"retry-after": "1"}, "body": "missing"}
+            }"#,
+        )
+        .expect("fixtures");
+
+        assert_eq!(fixtures["https://nhentai.net/g/1/"].status, 200);
+        assert_eq!(
+            fixtures["https://nhentai.net/g/1/"].body,
+            "<html>gallery</html>"
+        );
+        let missing = &fixtures["https://nhentai.net/g/2/"];
+        assert_eq!(missing.status, 404);
+        assert_eq!(missing.headers["retry-after"], "1");
+    }
+
+    #[test]
+    fn parse_fixtures_rejects_malformed_json() {
+        assert!(parse_fixtures("[1, 2]").is_none());
+        assert!(parse_fixtures(r#"{"u": {"status": "ok"}}"#).is_none());
+        assert_eq!(parse_fixtures("  "), Some(HashMap::new()));
+    }
+
+    #[test]
+    fn replay_serves_recorded_response_by_url() {
+        let transport = Transport::Replay(parse_fixtures(r#"{"https://a/": "body"}"#).unwrap());
+        let request = HttpRequest::new("https://a/");
+        assert_eq!(transport.send(&request).expect("recorded").body, "body");
+        assert!(transport.send(&HttpRequest::new("https://b/")).is_err());
+    }
+
+    #[test]
+    fn record_line_round_trips_through_parse_fixtures() {
+        let response = RecordedResponse {
+            status: 200,
+            headers: HashMap::new(),
+            body: "<p>\"x\"</p>".to_string(),
+        };
+        let line: Value = serde_json::from_str(&record_line("https://a/", &response)).unwrap();
+        assert_eq!(line["url"], "https://a/");
+        assert_eq!(parse_fixture(&line), Some(response));
+    }
+}
@@ -4,8 +4,8 @@ use extism::*;
 use rs_plugin_common_interfaces::{
     domain::rs_ids::RsIds,
     lookup::{
-        RsLookupBook, RsLookupMetadataResult, RsLookupMetadataResults,
-        RsLookupMetadataResultWrapper, RsLookupQuery, RsLookupSourceResult, RsLookupWrapper,
+        RsLookupBook, RsLookupMatchType, RsLookupMetadataResult, RsLookupMetadataResults,
+        RsLookupQuery, RsLookupSourceResult, RsLookupWrapper,
     },
     CustomParamTypes,
 };
@@ -16,6 +16,91 @@ fn build_plugin() -> Plugin {
     Plugin::new(&manifest, [], true).expect("Failed to create plugin")
 }
 
+const REPLAY_INDEX: &str = "tests/fixtures/replay.json";
+const REPLAY_DIR: &str = "tests/fixtures/replay";
+/// Base URL of the hand-written replay fixtures. It is a reserved test domain, so the
+/// recorder, which only talks to nhentai.net, never overwrites them.
+const REPLAY_BASE_URL: &str = "https://nhentai.test";
+/// Where `record_replay_fixtures` writes live captures, in the replay index format.
+const RECORD_DIR: &str = "target/nhentai-recorded";
+
+/// Plugin serving the synthetic pages listed in `tests/fixtures/replay.json`; no network.
+fn build_replay_plugin() -> Plugin {
+    let wasm = Wasm::file("target/wasm32-unknown-unknown/release/rs_plugin_nh.wasm");
+    let manifest = Manifest::new([wasm])
+        .with_config_key("nh_transport", "replay")
+        .with_config_key("nh_fixtures", load_replay_fixtures());
+    Plugin::new(&manifest, [], true).expect("Failed to create plugin")
+}
+
+/// Inlines each fixture file as the `body` of its entry, in the plugin's fixture format.
+fn load_replay_fixtures() -> String {
+    let index = std::fs::read_to_string(REPLAY_INDEX).expect("Failed to read replay index");
+    let index: serde_json::Value = serde_json::from_str(&index).expect("Invalid replay index");
+    let fixtures: serde_json::Map<String, serde_json::Value> = index
+        .as_object()
+        .expect("Replay index must be an object")
+        .iter()
+        .map(|(url, entry)| {
+            let file = entry["file"].as_str().expect("Fixture entry needs a file");
+            let body = std::fs::read_to_string(format!("{REPLAY_DIR}/{file}"))
+                .unwrap_or_else(|e| panic!("Failed to read fixture {file}: {e}"));
+            let fixture = serde_json::json!({
+                "status": entry.get("status").cloned().unwrap_or(200.into()),
+                "headers": entry.get("headers").cloned().unwrap_or_default(),
+                "body": body,
+            });
+            (url.clone(), fixture)
+        })
+        .collect();
+    serde_json::Value::Object(fixtures).to_string()
+}
+
+fn book_lookup(
+    name: Option<&str>,
+    ids: Option<Vec<&str>>,
+    page_key: Option<&str>,
+) -> RsLookupWrapper {
+    RsLookupWrapper {
+        query: RsLookupQuery::Book(RsLookupBook {
+            name: name.map(str::to_string),
+            ids: ids.map(|ids| {
+                RsIds::try_from(ids.into_iter().map(str::to_string).collect::<Vec<_>>()).unwrap()
+            }),
+            page_key: page_key.map(str::to_string),
+        }),
+        credential: None,
+        params: None,
+    }
+}
+
+/// `book_lookup` pointed at the synthetic replay fixtures.
+fn replay_lookup(
+    name: Option<&str>,
+    ids: Option<Vec<&str>>,
+    page_key: Option<&str>,
+) -> RsLookupWrapper {
+    let mut input = book_lookup(name, ids, page_key);
+    input.params = Some(HashMap::from([(
+        "base_url".to_string(),
+        CustomParamTypes::Text(Some(REPLAY_BASE_URL.to_string())),
+    )]));
+    input
+}
+
+/// Lookups the replay tests run, which between them fetch every entry of the replay index.
+fn replay_scenarios() -> Vec<RsLookupWrapper> {
+    vec![
+        book_lookup(Some("nhentai:12345"), None, None),
+        book_lookup(Some("soft"), None, None),
+        book_lookup(Some("soft"), None, Some("2")),
+        book_lookup(Some("Soft Sample Two"), None, None),
+        book_lookup(Some("soft"), Some(vec!["nhentai:99999999"]), None),
+        book_lookup(Some("nhentai-artist:artist-one"), None, None),
+        book_lookup(Some("nhentai:random"), None, None),
+    ]
+}
+
 fn call_lookup_source(plugin: &mut Plugin, input: &RsLookupWrapper) -> RsLookupSourceResult {
     let input_str = serde_json::to_string(input).unwrap();
     let output = plugin
@@ -540,3 +625,299 @@ fn test_lookup_metadata_with_custom_search_params() {
         id_without, id_with
     );
 }
+
+#[test]
+fn test_lookup_direct_id_629637_api_backend() {
+    let mut plugin = build_plugin();
+
+    let mut params = HashMap::new();
+    params.insert(
+        "backend".to_string(),
+        CustomParamTypes::Text(Some("api".to_string())),
+    );
+
+    let input = RsLookupWrapper {
+        query: RsLookupQuery::Book(RsLookupBook {
+            name: Some("nhentai:629637".to_string()),
+            ids: None,
+            page_key: None,
+        }),
+        credential: None,
+        params: Some(params),
+    };
+
+    let results = call_lookup(&mut plugin, &input);
+    assert!(
+        !results.results.is_empty(),
+        "Expected at least one result for direct id nhentai:629637 through the API backend"
+    );
+
+    let book = match &results.results[0].metadata {
+        RsLookupMetadataResult::Book(book) => book,
+        _ => panic!("Expected book metadata"),
+    };
+    assert_eq!(book.id, "nhentai:629637");
+    assert!(
+        book.params
+            .as_ref()
+            .and_then(|v| v.get("artists"))
+            .and_then(|v| v.as_array())
+            .map(|arr| !arr.is_empty())
+            .unwrap_or(false),
+        "Expected at least one artist extracted from the API payload"
+    );
+}
+
+#[test]
+fn test_replay_direct_id_returns_full_gallery() {
+    let mut plugin = build_replay_plugin();
+
+    let results = call_lookup(
+        &mut plugin,
+        &replay_lookup(Some("nhentai:12345"), None, None),
+    );
+    assert_eq!(results.results.len(), 1);
+    assert_eq!(results.next_page_key, None);
+    let book = match &results.results[0].metadata {
+        RsLookupMetadataResult::Book(book) => book,
+        _ => panic!("Expected book metadata"),
+    };
+    assert_eq!(book.id, "nhentai:12345");
+    assert_eq!(book.name, "Soft Sample One");
+}
+
+#[test]
+fn test_replay_search_pagination() {
+    let mut plugin = build_replay_plugin();
+
+    let first = call_lookup(&mut plugin, &replay_lookup(Some("soft"), None, None));
+    assert_eq!(first.results.len(), 2);
+    assert_eq!(first.next_page_key, Some("2".to_string()));
+
+    let second = call_lookup(&mut plugin, &replay_lookup(Some("soft"), None, Some("2")));
+    assert_eq!(second.results.len(), 1);
+    assert_eq!(second.next_page_key, None);
+}
+
+#[test]
+fn test_replay_search_cards_resolve_data_tags() {
+    let mut plugin = build_replay_plugin();
+
+    let results = call_lookup(&mut plugin, &replay_lookup(Some("soft"), None, None));
+    assert_eq!(results.results.len(), 2);
+    let book = |idx: usize| match &results.results[idx].metadata {
+        RsLookupMetadataResult::Book(book) => book.clone(),
+        _ => panic!("Expected book metadata"),
+    };
+    let param = |idx: usize, name: &str| book(idx).params.as_ref().unwrap()[name].clone();
+
+    assert_eq!(book(0).lang.as_deref(), Some("en"));
+    assert_eq!(param(0, "languages"), serde_json::json!(["english"]));
+    assert_eq!(param(0, "categories"), serde_json::json!(["doujinshi"]));
+    assert_eq!(
+        param(1, "languages"),
+        serde_json::json!(["english", "translated"])
+    );
+    assert_eq!(param(1, "categories"), serde_json::json!(["manga"]));
+}
+
+#[test]
+fn test_replay_lookup_returns_group_with_pages() {
+    let mut plugin = build_replay_plugin();
+
+    let result = call_lookup_source(
+        &mut plugin,
+        &replay_lookup(Some("nhentai:12345"), None, None),
+    );
+    let RsLookupSourceResult::GroupRequest(groups) = result else {
+        panic!("Expected GroupRequest for direct id");
+    };
+    assert_eq!(groups.len(), 1);
+    assert_eq!(
+        groups[0]
+            .requests
+            .iter()
+            .map(|r| r.url.as_str())
+            .collect::<Vec<_>>(),
+        vec![
+            "https://i.nhentai.net/galleries/555/1.jpg",
+            "https://i.nhentai.net/galleries/555/2.jpg",
+            "https://i.nhentai.net/galleries/555/3.png",
+        ]
+    );
+}
+
+#[test]
+fn test_replay_unknown_id_falls_back_to_name_search() {
+    let mut plugin = build_replay_plugin();
+
+    let input = replay_lookup(Some("soft"), Some(vec!["nhentai:99999999"]), None);
+    let results = call_lookup(&mut plugin, &input);
+    assert_eq!(results.results.len(), 2);
+    assert_eq!(results.next_page_key, Some("2".to_string()));
+}
+
+/// Captures the live pages behind the replay scenarios:
+/// `cargo test -- --ignored record_replay_fixtures`. Responses are taken from the plugin's
+/// record-mode log lines and written to `target/nhentai-recorded` with their own index, so
+/// they can be diffed against the synthetic fixtures without replacing them. Fails when an
+/// entry of the replay index has no live counterpart.
+#[test]
+#[ignore]
+fn record_replay_fixtures() {
+    let lines = std::sync::Arc::new(std::sync::Mutex::new(Vec::<String>::new()));
+    let sink = lines.clone();
+    extism::set_log_callback(
+        move |line| sink.lock().unwrap().push(line.to_string()),
+        "info",
+    )
+    .expect("Failed to install log callback");
+
+    let wasm = Wasm::file("target/wasm32-unknown-unknown/release/rs_plugin_nh.wasm");
+    let manifest = Manifest::new([wasm])
+        .with_allowed_host("nhentai.net")
+        .with_config_key("nh_transport", "record");
+    let mut plugin = Plugin::new(&manifest, [], true).expect("Failed to create plugin");
+
+    for input in replay_scenarios() {
+        let input = serde_json::to_string(&input).unwrap();
+        // Failed lookups (the unknown id) still log the responses they received.
+        let _ = plugin.call::<&str, &[u8]>("lookup_metadata", &input);
+    }
+
+    std::fs::create_dir_all(RECORD_DIR).expect("Failed to create record dir");
+    let mut recorded = serde_json::Map::new();
+    for line in lines.lock().unwrap().iter() {
+        let Some(json) = line
+            .split_once("nhentai-record ")
+            .map(|(_, json)| json.trim())
+        else {
+            continue;
+        };
+        let record: serde_json::Value = serde_json::from_str(json).expect("Invalid record line");
+        let url = record["url"].as_str().unwrap();
+        let file = format!("{}.html", recorded.len());
+        std::fs::write(
+            format!("{RECORD_DIR}/{file}"),
+            record["body"].as_str().unwrap_or_default(),
+        )
+        .expect("Failed to write capture");
+        recorded.insert(
+            url.to_string(),
+            serde_json::json!({
+                "status": record["status"],
+                "headers": record["headers"],
+                "file": file,
+            }),
+        );
+        println!("Recorded {url} -> {file}");
+    }
+    std::fs::write(
+        format!("{RECORD_DIR}/index.json"),
+        serde_json::to_string_pretty(&recorded).unwrap(),
+    )
+    .expect("Failed to write capture index");
+
+    let index = std::fs::read_to_string(REPLAY_INDEX).expect("Failed to read replay index");
+    let index: serde_json::Value = serde_json::from_str(&index).expect("Invalid replay index");
+    let missing: Vec<String> = index
+        .as_object()
+        .expect("Replay index must be an object")
+        .keys()
+        .map(|url| url.replacen(REPLAY_BASE_URL, "https://nhentai.net", 1))
+        .filter(|url| !recorded.contains_key(url))
+        .collect();
+    assert!(
+        missing.is_empty(),
+        "Replay scenarios did not fetch: {missing:?}"
+    );
+}
+
+#[test]
+fn test_replay_relation_id_uses_listing_page() {
+    let mut plugin = build_replay_plugin();
+
+    let input = replay_lookup(Some("nhentai-artist:artist-one"), None, None);
+    let results = call_lookup(&mut plugin, &input);
+    assert_eq!(results.results.len(), 1);
+    assert_eq!(results.next_page_key, None);
+}
+
+#[test]
+fn test_replay_search_enrichment_fetches_first_hits() {
+    let mut plugin = build_replay_plugin();
+
+    let mut input = replay_lookup(Some("soft"), None, None);
+    input.params.get_or_insert_with(HashMap::new).insert(
+        "enrich_results".to_string(),
+        CustomParamTypes::UInteger(Some(1)),
+    );
+    let results = call_lookup(&mut plugin, &input);
+    assert_eq!(results.results.len(), 2);
+
+    let artists = |idx: usize| match &results.results[idx].metadata {
+        RsLookupMetadataResult::Book(book) => book
+            .params
+            .as_ref()
+            .and_then(|v| v.get("artists"))
+            .and_then(|v| v.as_array())
+            .map_or(0, Vec::len),
+        _ => panic!("Expected book metadata"),
+    };
+    assert_eq!(artists(0), 1, "first hit should be enriched");
+    assert_eq!(artists(1), 0, "hits past the cap keep the search card");
+}
+
+#[test]
+fn test_replay_search_ranks_near_exact_title_first() {
+    let mut plugin = build_replay_plugin();
+
+    let results = call_lookup(
+        &mut plugin,
+        &replay_lookup(Some("Soft Sample Two"), None, None),
+    );
+    assert_eq!(results.results.len(), 2);
+    let book = match &results.results[0].metadata {
+        RsLookupMetadataResult::Book(book) => book,
+        _ => panic!("Expected book metadata"),
+    };
+    assert_eq!(book.id, "nhentai:12346");
+    assert_eq!(
+        results.results[0].match_type,
+        Some(RsLookupMatchType::ExactText)
+    );
+    assert_eq!(results.results[1].match_type, None);
+}
+
+#[test]
+fn test_replay_lookup_ranks_search_downloads() {
+    let mut plugin = build_replay_plugin();
+
+    let result = call_lookup_source(
+        &mut plugin,
+        &replay_lookup(Some("Soft Sample Two"), None, None),
+    );
+    let RsLookupSourceResult::GroupRequest(groups) = result else {
+        panic!("Expected GroupRequest for a name search");
+    };
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0].match_type, Some(RsLookupMatchType::ExactText));
+    assert_eq!(groups[1].match_type, None);
+}
+
+#[test]
+fn test_replay_random_returns_full_gallery() {
+    let mut plugin = build_replay_plugin();
+
+    let results = call_lookup(
+        &mut plugin,
+        &replay_lookup(Some("nhentai:random"), None, None),
+    );
+    assert_eq!(results.results.len(), 1);
+    let book = match &results.results[0].metadata {
+        RsLookupMetadataResult::Book(book) => book,
+        _ => panic!("Expected book metadata"),
+    };
+    assert_eq!(book.id, "nhentai:12345");
+    assert_eq!(book.name, "Soft Sample One");
+}